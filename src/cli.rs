@@ -1,9 +1,31 @@
 use clap::{Parser, Subcommand};
 
+use crate::utils::credentials::CredentialStore;
+use crate::utils::filter::TaskFilter;
+use crate::utils::output::OutputFormat;
+use crate::utils::selection::{IdRange, TaskSelection};
+use crate::utils::sort::TaskOrder;
+use crate::utils::tabular::TaskColumn;
+
 // Defining the CLI Struct
 #[derive(Parser)]
 #[command(name = "cues", version, about = "A todo list cli")]
 pub struct Cli {
+    #[arg(long, global=true, help="Backend API base URL (overrides CUES_API_URL and config)")]
+    pub api_url: Option<String>,
+
+    #[arg(long, global=true, value_enum, help="Where to keep login credentials (overrides config)")]
+    pub credential_store: Option<CredentialStore>,
+
+    #[arg(long, global=true, help="Profile to use for this command (overrides CUES_PROFILE and the active profile)")]
+    pub profile: Option<String>,
+
+    #[arg(long, global=true, help="Print the requests that would change anything, without sending them")]
+    pub dry_run: bool,
+
+    #[arg(short, long, global=true, value_enum, default_value_t, help="Output format (json and jsonl are meant for scripts, table/csv/tsv apply to task listings)")]
+    pub output: OutputFormat,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -15,7 +37,8 @@ pub enum Commands {
     // COMMANDS: Projects
     Projects,
     Use {
-        pid: u32,
+        #[arg(help="Project id or name")]
+        project: String,
     },
     #[command(aliases=["current", "active"])]
     Cwp,
@@ -23,10 +46,20 @@ pub enum Commands {
         #[command(subcommand)]
         kind: NewProject
     },
+    Link {
+        #[arg(help="Project id or name")]
+        project: String,
+    },
+    Unlink,
+    Project {
+        #[command(subcommand)]
+        action: ProjectAction,
+    },
 
     // COMMANDS: Tasks
     Add {
-        title: String,
+        #[arg(required_unless_present="editor")]
+        title: Option<String>,
 
         #[arg(short, long, help="Task priority", value_enum)]
         priority: Option<Priority>,
@@ -36,16 +69,48 @@ pub enum Commands {
 
         #[arg(short='u', long, help="Task due date & time")]
         due: Option<String>,
+
+        #[arg(short, long, help="Write the task in $VISUAL/$EDITOR (other flags fill in the document)")]
+        editor: bool,
     },
     Tasks {
         #[arg(short, long, help="List tasks in all projects")]
         all: bool,
+
+        #[arg(short, long, help="List tasks in another project (id or name)", conflicts_with="all")]
+        project: Option<String>,
+
+        #[arg(long, value_enum, value_delimiter=',', help="Columns to show with --output table, csv or tsv (comma-separated)")]
+        columns: Vec<TaskColumn>,
+
+        #[command(flatten)]
+        filter: TaskFilter,
+
+        #[command(flatten)]
+        order: TaskOrder,
     },
-    Done {
+    Show {
         task_id: u32,
     },
+    Done {
+        #[command(flatten)]
+        selection: TaskSelection,
+    },
+    #[command(alias="reopen")]
+    Undone {
+        #[command(flatten)]
+        selection: TaskSelection,
+    },
+    Toggle {
+        #[command(flatten)]
+        selection: TaskSelection,
+    },
     Edit {
-        task_id: u32,
+        #[arg(value_name="IDS", help="Task ids or ranges (eg, 4 7 10-14)")]
+        task_ids: Vec<IdRange>,
+
+        #[arg(long, value_name="CRITERIA", help="Select tasks like the tasks command filters (eg, \"pending project=3 priority=low\")")]
+        filter: Option<String>,
 
         #[arg(short, long, help="Task title")]
         title: Option<String>,
@@ -61,15 +126,95 @@ pub enum Commands {
 
         #[arg(short='D', long, help="Task done status")]
         done: Option<bool>,
+
+        #[arg(long, help="Remove the priority", conflicts_with="priority")]
+        no_priority: bool,
+
+        #[arg(long, help="Remove the description", conflicts_with="desc")]
+        no_desc: bool,
+
+        #[arg(long, help="Remove the due date", conflicts_with="due")]
+        no_due: bool,
+
+        #[arg(short, long, help="Edit every field of one task in $VISUAL/$EDITOR",
+            conflicts_with_all=["filter", "title", "priority", "desc", "due", "done", "no_priority", "no_desc", "no_due"])]
+        interactive: bool,
     },
     Delete {
-        task_id: u32,
+        #[command(flatten)]
+        selection: TaskSelection,
+
+        #[arg(short, long, help="Delete without asking for confirmation")]
+        yes: bool,
+    },
+    // Move tasks to another project
+    Move {
+        #[command(flatten)]
+        selection: TaskSelection,
+
+        #[arg(long, value_name="PROJECT", help="Project to move the tasks to (id or name)")]
+        to: String,
+    },
+    // Revert the latest add, edit, done/undone/toggle, move or delete
+    Undo,
+    // List recent operations that can be undone
+    History {
+        #[arg(short='n', long, default_value_t=10, help="How many operations to show")]
+        limit: usize,
     },
 
     // COMMANDS: Authentication
-    Login,
+    Login {
+        #[arg(short, long, help="Username or email (or set CUES_USERNAME)")]
+        username: Option<String>,
+
+        #[arg(long, help="Read the password from stdin (or set CUES_PASSWORD)")]
+        password_stdin: bool,
+    },
     Logout,
     Whoami,
+
+    // COMMANDS: Profiles
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ProjectAction {
+    Rename {
+        #[arg(help="Project id or name")]
+        project: String,
+        name: String,
+    },
+    Delete {
+        #[arg(help="Project id or name")]
+        project: String,
+
+        #[arg(short, long, help="Delete without asking for confirmation")]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ProfileAction {
+    // Create a profile (takes the global --api-url and --credential-store for it)
+    Add {
+        name: String,
+    },
+    // Switch the active profile
+    Use {
+        name: String,
+    },
+    // List all profiles
+    #[command(alias="ls")]
+    List,
+    // Delete a profile and its stored credentials
+    #[command(alias="rm")]
+    Remove {
+        name: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -79,7 +224,7 @@ pub enum NewProject {
     }
 }
 
-#[derive(clap::ValueEnum, Clone)]
+#[derive(clap::ValueEnum, Clone, Debug)]
 pub enum Priority {
     High,
     Medium,
@@ -1,209 +1,247 @@
-use std::{error, fs, io::{self, Write}, process::exit};
-use std::path::PathBuf;
-use chrono::{Duration, Local};
+use std::{error, fs, io::{self, Write}};
+use chrono::{DateTime, Local};
 use colored::Colorize;
-use keyring::Entry;
 use rpassword::read_password;
 use serde_json::json;
-use crate::{cli::{Cli, Commands, NewProject, Priority}, utils::{api::post_login, auth::{jwt_expired, refresh_access_token}, display_format::log_err}};
-use crate::utils::api::{delete_task, get_projects, get_tasks, get_uniq_proj, get_user, post_project, post_task, put_task};
-use crate::utils::config_path::{get_config_path, load_config, Config};
-use crate::utils::display_format::{format_pretty_date, natural_to_datetime, print_project, print_task};
-use crate::utils::types::{PriorityType, Project, Task, User};
-use crate::utils::auth::AuthStore;
+use crate::{cli::{Cli, Commands, NewProject, Priority, ProfileAction, ProjectAction}, utils::display_format::log_err};
+use crate::utils::api::{is_dry_run, resolve_base_url, CuesClient};
+use crate::utils::config_path::{active_profile, load_config, load_config_file, save_config, save_config_file, Config, DEFAULT_PROFILE};
+use crate::utils::dates::natural_to_datetime;
+use crate::utils::display_format::{format_pretty_date, log_failure, print_project, print_task, print_task_detail};
+use crate::utils::editor::{create_draft, open_editor, TaskDraft};
+use crate::utils::error::ApiError;
+use crate::utils::filter::TaskFilter;
+use crate::utils::history::{load_history, record, restore_payload, settle_last, HistoryEntry, Operation};
+use crate::utils::link::{current_project, find_link, write_link, ProjectLink, ProjectSource};
+use crate::utils::output::{emit, emit_error, emit_list, is_machine, is_tabular, mark_failed};
+use crate::utils::projects::resolve_project;
+use crate::utils::selection::{run_bulk, TaskScope, TaskSelection};
+use crate::utils::sort::TaskOrder;
+use crate::utils::tabular::{print_tasks_tabular, TaskColumn};
+use crate::utils::session::{connect, env_var};
+use crate::utils::types::{PriorityType, Task};
+use crate::utils::auth::{token_expiry, AuthStore, ACCESS_TOKEN, REFRESH_TOKEN};
+use crate::utils::credentials::{keyring_available, CredentialError, CredentialStore};
 
 
 
 // HANDLER: Use project
-async fn handle_use (pid: u32, auth_store: &AuthStore) -> Result<(), Box<dyn error::Error>> {
-    if let Some(mut config) = load_config() {
-        // Get token from keyring
-        let mut token = match auth_store.access.get_password() {
-            Ok(t) => t,
-            Err(_) => {
-                println!("\n{} You may not be logged in. Run {}", " ".red(), "cues login".yellow());
-                exit(0);
-            }
-        };
+async fn handle_use (query: String, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    let mut config = load_config().unwrap_or_default();
 
-        // Check if token is expired, if yes then refresh and get a new token
-        if jwt_expired(config.expires_at.as_str()) {
-            let refresh_token = match auth_store.refresh.get_password() {
-                Ok(t) => t,
-                Err(_) => {
-                    println!("\n{} Refresh token couldn't be found. Log in again by running {}", " ".red(), "cues login".yellow());
-                    exit(0);
-                }
+    // API call (find the project by id or name)
+    let proj = resolve_project(client, &query).await?;
+
+    // Setting config file properties and writing them to file
+    config.current_project_id = proj.id;
+    config.current_project = proj.name.clone();
+    save_config(&config)?;
+
+    if is_machine() {
+        emit(&proj);
+        return Ok(());
+    }
+
+    println!("\n{}\n{}", "  Set active project:".green(), proj.name.clone());
+
+    Ok(())
+}
+
+// HANDLER: Get current active/working project
+async fn handle_cwp () -> Result<(), Box<dyn error::Error>> {
+    if is_machine() {
+        let cwp = current_project().map(|cwp| {
+            let (source, path) = match cwp.source {
+                ProjectSource::Link(path) => ("link", Some(path)),
+                ProjectSource::Global => ("global", None),
             };
+            json!({ "id": cwp.id, "name": cwp.name, "source": source, "path": path })
+        });
 
-            let new_token_res: String = refresh_access_token(&refresh_token).await?;
+        emit(&cwp);
+        return Ok(());
+    }
 
-            auth_store.access.set_password(&new_token_res)?;
-            token = new_token_res;
-        }
+    match current_project() {
+        Some(cwp) => {
+            // Log the current project as CWP (Current Working Project), and where it was set
+            let pid = format!("[{}]", cwp.id).yellow();
+            println!("\n{}\n{} {}", "Active Project:".yellow(), pid, cwp.name);
 
-        // API call
-        let res = get_uniq_proj(token.as_str(), &pid).await?;
+            match cwp.source {
+                ProjectSource::Link(path) => println!("\n{} {}", "Linked in".dimmed(), path.display()),
+                ProjectSource::Global => println!("\n{} {}", "Set globally with".dimmed(), "cues use".dimmed()),
+            }
+        },
+        None if load_config().is_some() => {
+            // No active project is set. So, logging this info to user in order to tell them what to be done.
+            println!("\n{} You have not set any project as active. Log in using {} and run {} to set an active project.", " ".bold().red(), "cues login".yellow(), "cues use".yellow());
+        },
+        None => {
+            println!("\n{} Config file missing. Run {} to log in to your account.", " ".red(), "cues login".yellow());
+        },
+    }
 
-        // Extracting value from response
-        if let Some(projval) = res.get("project") {
-            let proj: Project = serde_json::from_value(projval.clone())?;
+    Ok(())
+}
 
-            // Setting config file properties
-            config.current_project_id = pid.clone();
-            config.current_project = proj.name.clone();
+// HANDLER: Bind the current directory (and everything below it) to a project
+async fn handle_link (query: String, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    // API call (find the project by id or name)
+    let project = resolve_project(client, &query).await?;
 
-            // Writing new properties to file
-            let config_path = get_config_path().expect("Could not determine config directory");
-            let json = serde_json::to_string_pretty(&config)?;
-            fs::write(&config_path, json)?;
+    let link = ProjectLink { project_id: project.id, project: project.name.clone() };
+    let path = write_link(&std::env::current_dir()?, &link)?;
 
-            println!("\n{}\n{}", "  Set active project:".green(), proj.name.clone());
-        } else {
-            println!();
-            log_err(res);
-        }
-    } else {
-        println!("\n{} Config file missing. Run {} to log in to your account.", " ".red(), "cues login".yellow());
+    if is_machine() {
+        emit(&json!({ "project": project, "path": path }));
+        return Ok(());
     }
 
+    println!("\n{} Linked this directory to {} {}", "".green(), format!("[{}]", project.id).yellow(), project.name);
+    println!("{} {}", "Wrote".dimmed(), path.display().to_string().dimmed());
+
     Ok(())
 }
 
-// HANDLER: Get current active/working project
-async fn handle_cwp (auth_store: &AuthStore) -> Result<(), Box<dyn error::Error>> {
-    if let Some(config) = load_config() {
-        if (config.current_project_id == 0) {
-            // If current_project_id == 0, it means no active project is set. So, logging this info
-            // to user in order to tell them what to be done.
-            println!("\n{} {} {} {} {} {}", " ".bold().red(), "You have not set any project as active. Log in using", "cues login".yellow(), "and run", "cues use".yellow(), "to set an active project.");
-        } else {
-            // Else, get the current project name and log it as CWP (Current Working Project)
-            let cwp = config.current_project;
-            let pid = format!("[{}]", config.current_project_id).yellow();
-            println!("\n{}\n{} {}", "Active Project:".yellow(), pid, cwp);
-        }
-    } else {
-        println!("\n{} Config file missing. Run {} to log in to your account.", " ".red(), "cues login".yellow());
+// HANDLER: Remove the nearest project binding
+async fn handle_unlink () -> Result<(), Box<dyn error::Error>> {
+    match find_link() {
+        Some((path, _)) if is_machine() => {
+            std::fs::remove_file(&path)?;
+            emit(&json!({ "removed": path }));
+        },
+        None if is_machine() => emit(&json!({ "removed": null })),
+        Some((path, _)) => {
+            std::fs::remove_file(&path)?;
+            println!("\n{} Removed {}", "".green(), path.display());
+        },
+        None => println!("\n{} This directory isn't linked to a project.", "".yellow()),
     }
 
     Ok(())
 }
 
 // HANDLER: Create new project
-async fn handle_new_project (name: String, auth_store: &AuthStore) -> Result<(), Box<dyn error::Error>> {
-    if let Some(config) = load_config() {
-        // Get token from keyring
-        let mut token = match auth_store.access.get_password() {
-            Ok(t) => t,
-            Err(_) => {
-                println!("\n{} You may not be logged in. Run {}", " ".red(), "cues login".yellow());
-                exit(0);
-            }
-        };
+async fn handle_new_project (name: String, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    // API call
+    let project = client.create_project(&name).await?;
 
-        // Check if token is expired, if yes then refresh and get a new token
-        if jwt_expired(config.expires_at.as_str()) {
-            let refresh_token = match auth_store.refresh.get_password() {
-                Ok(t) => t,
-                Err(_) => {
-                    println!("\n{} Refresh token couldn't be found. Log in again by running {}", " ".red(), "cues login".yellow());
-                    exit(0);
-                }
-            };
+    if is_machine() {
+        emit(&project);
+        return Ok(());
+    }
 
-            let new_token_res: String = refresh_access_token(&refresh_token).await?;
+    println!("\n{} The following project was added:\n", " ".green());
+    print_project(project);
 
-            auth_store.access.set_password(&new_token_res)?;
-            token = new_token_res;
-        }
+    Ok(())
+}
 
-        let payload = json!({
-            "name": name.clone()
-        });
+// HANDLER: List all projects
+async fn handle_list_projects (client: &CuesClient) -> Result<(), Box<dyn std::error::Error>> {
+    // API call
+    let projects = client.list_projects().await?;
+
+    if is_machine() {
+        emit_list(&projects);
+        return Ok(());
+    }
+
+    // Pretty-printing all projects
+    println!("\nProjects:\n");
+    for proj in projects {
+        print_project(proj);
+    }
 
-        // API call
-        let res = post_project(token.as_str(), &payload).await?;
+    Ok(())
+}
 
-        // Extracting data from response
-        if let Some(projval) = res.get("project") {
-            let project: Project = serde_json::from_value(projval.clone())?;
+// HANDLER: Rename project
+async fn handle_project_rename (query: String, name: String, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    let pid = resolve_project(client, &query).await?.id;
 
-            let pname = &project.name;
-            let pid = project.id;
+    // API call
+    let project = client.update_project(pid, &json!({ "name": name })).await?;
 
-            println!("\n{} The following project was added:\n", " ".green());
-            print_project(project);
-        } else {
-            println!();
-            log_err(res);
+    // Keep the stored name of the active project in sync
+    if let Some(mut config) = load_config() {
+        if config.current_project_id == pid {
+            config.current_project = project.name.clone();
+            save_config(&config)?;
         }
-    } else {
-        println!("\n{} You need to log in first to run this command. Run {} to log in to your account.", " ".red(), "cues login".yellow());
     }
 
+    if is_machine() {
+        emit(&project);
+        return Ok(());
+    }
+
+    println!("\n{} The project was renamed:\n", "".green());
+    print_project(project);
+
     Ok(())
 }
 
-// HANDLER: List all projects
-async fn handle_list_projects (auth_store: &AuthStore) -> Result<(), Box<dyn std::error::Error>> {
-    if let Some(config) = load_config() {
-        // Get token from keyring
-        let mut token = match auth_store.access.get_password() {
-            Ok(t) => t,
-            Err(_) => {
-                println!("\n{} You may not be logged in. Run {}", " ".red(), "cues login".yellow());
-                exit(0);
+// HANDLER: Delete project
+async fn handle_project_delete (query: String, yes: bool, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    // Concurrently find the project and fetch the tasks, to show what will be removed
+    let (project, tasks) = tokio::join!(
+        resolve_project(client, &query),
+        client.list_tasks(),
+    );
+
+    let project = project?;
+    let pid = project.id;
+    let tasks: Vec<Task> = tasks?.into_iter().filter(|t| t.project_id == pid).collect();
+    let task_count = tasks.len();
+
+    if !yes && !is_dry_run() {
+        if !is_machine() && !tasks.is_empty() {
+            println!("\n{} {}\n", "".yellow(), "Following task(s) will be deleted along with the project:".yellow());
+            for task in &tasks {
+                print_task(task, false)?;
             }
-        };
-
-        // Check if token is expired, if yes then refresh and get a new token
-        if jwt_expired(config.expires_at.as_str()) {
-            let refresh_token = match auth_store.refresh.get_password() {
-                Ok(t) => t,
-                Err(_) => {
-                    println!("\n{} Refresh token couldn't be found. Log in again by running {}", " ".red(), "cues login".yellow());
-                    exit(0);
-                }
-            };
-
-            let new_token_res: String = refresh_access_token(&refresh_token).await?;
-
-            auth_store.access.set_password(&new_token_res)?;
-            token = new_token_res;
         }
 
-        // API call
-        let res = get_projects(token.as_str()).await?;
+        let prompt = format!("Delete project {} {} and its {} task(s)?", format!("[{}]", project.id).yellow(), project.name.bold(), task_count);
+        if !confirm(&prompt)? {
+            report_cancelled();
+            return Ok(());
+        }
+    }
 
-        // Extracting data from response
-        if let Some(projects_val) = res.get("projects") {
-            let projects: Vec<Project> = serde_json::from_value(projects_val.clone())?;
+    // API call
+    let project = client.delete_project(pid).await?;
 
-            // Pretty-printing all projects
-            println!("\nProjects:\n");
-            for proj in projects {
-                print_project(proj);
-            }
-        } else {
-            println!();
-            log_err(res);
+    // Deleting the active project leaves no active project
+    if let Some(mut config) = load_config() {
+        if config.current_project_id == pid {
+            config.current_project = String::new();
+            config.current_project_id = 0;
+            save_config(&config)?;
         }
-    } else {
-        println!("\n{} You need to log in first to run this command. Run {} to log in to your account.", " ".red(), "cues login".yellow());
     }
 
+    if is_machine() {
+        emit(&project);
+        return Ok(());
+    }
+
+    println!("\n{} The following project was deleted, along with {} task(s):\n", "".green(), task_count);
+    print_project(project);
+
     Ok(())
 }
 
 // HANDLER: Add new task
-async fn handle_task_add (title: String, desc: Option<String>, due: Option<String>, priority: Option<PriorityType>, auth_store: &AuthStore) -> Result<(), Box<dyn error::Error>> {
+async fn handle_task_add (title: String, desc: Option<String>, due: Option<String>, priority: Option<PriorityType>, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
     // Parse custom format ("today 16:00" or "friday 4:00" etc) into proper datetime format
     let parsed_due = if let Some(due_date) = due {
         match natural_to_datetime(&due_date) {
             Some(due_str) => Some(due_str),
             None => {
-                eprintln!("{} Invalid due date format.", " ".red());
+                log_failure("Invalid due date format.".to_string());
                 return Ok(());
             }
         }
@@ -211,434 +249,499 @@ async fn handle_task_add (title: String, desc: Option<String>, due: Option<Strin
         None
     };
 
-    if let Some(config) = load_config() {
-        // Get token from keyring
-        let mut token = match auth_store.access.get_password() {
-            Ok(t) => t,
-            Err(_) => {
-                println!("\n{} You may not be logged in. Run {}", " ".red(), "cues login".yellow());
-                exit(0);
-            }
-        };
+    // Linked project of this directory, else the global one
+    let pid = current_project().map(|p| p.id).unwrap_or(0);
 
-        // Check if token is expired, if yes then refresh and get a new token
-        if jwt_expired(config.expires_at.as_str()) {
-            let refresh_token = match auth_store.refresh.get_password() {
-                Ok(t) => t,
-                Err(_) => {
-                    println!("\n{} Refresh token couldn't be found. Log in again by running {}", " ".red(), "cues login".yellow());
-                    exit(0);
-                }
-            };
+    // Building payload one-by-one based on data provided by user
+    let mut payload = json!({
+        "title": title,
+        "projectId": pid,
+    });
 
-            let new_token_res: String = refresh_access_token(&refresh_token).await?;
+    if let Some(d) = desc {
+        payload["description"] = json!(d);
+    }
 
-            auth_store.access.set_password(&new_token_res)?;
-            token = new_token_res;
-        }
+    if let Some(due_str) = parsed_due {
+        payload["due"] = json!(due_str);
+    }
 
-        // Building payload one-by-one based on data provided by user
-        let mut payload = json!({
-            "title": title,
-            "projectId": config.current_project_id,
-        });
+    if let Some(p) = priority {
+        payload["priority"] = json!(format!("{:?}", p));
+    }
 
-        if let Some(d) = desc {
-            payload["description"] = json!(d);
-        }
+    // API call
+    let task = client.create_task(&payload).await?;
+    record(Operation::Add, vec![task.clone()]);
 
-        if let Some(due_str) = parsed_due {
-            payload["due"] = json!(due_str);
-        }
+    if is_machine() {
+        emit(&task);
+        return Ok(());
+    }
 
-        if let Some(p) = priority {
-            payload["priority"] = json!(format!("{:?}", p));
+    println!("\n{} The following task was added:\n", " ".green());
+    print_task(&task, false)?;  // Pretty-printing task
+
+    Ok(())
+}
+
+// HANDLER: List tasks (in cwp)
+async fn handle_list_tasks (all: bool, project: Option<String>, columns: Vec<TaskColumn>, filter: TaskFilter, order: TaskOrder, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    // Parse the date filters up front, before any API call
+    let matcher = match filter.matcher() {
+        Ok(matcher) => matcher,
+        Err(msg) => {
+            log_failure(msg);
+            return Ok(());
         }
+    };
+
+    // Get project id from --project, else the linked or global current project
+    let pid = match project {
+        Some(query) => resolve_project(client, &query).await?.id,
+        None => current_project().map(|p| p.id).unwrap_or(0),
+    };
+
+    // Rows for tables and spreadsheets, with project names looked up for the project column
+    if is_tabular() {
+        let (tasks, projects) = tokio::join!(
+            client.list_tasks(),
+            client.list_projects(),
+        );
 
-        // API call
-        let res = post_task(token.as_str(), &payload).await?;
+        let tasks = tasks?;
+        let mut shown: Vec<&Task> = tasks.iter().filter(|t| (all || t.project_id == pid) && matcher.matches(t)).collect();
+        order.apply(&mut shown);
 
-        // Extracting data from response
-        if let Some(taskval) = res.get("task") {
-            let task: Task = serde_json::from_value(taskval.clone())?;
+        return print_tasks_tabular(&shown, &projects?, &columns);
+    }
+
+    // For 'cues tasks --all'
+    if all {
+        // Concurrently make two API calls
+        let (tasks, projects) = tokio::join!(
+            client.list_tasks(),      // API call (Get tasks)
+            client.list_projects(),   // API call (Get projects)
+        );
+
+        let mut tasks: Vec<Task> = tasks?.into_iter().filter(|t| matcher.matches(t)).collect();
+        let projects = projects?;
+
+        // Sorting the whole list keeps the same order within every project group
+        order.apply(&mut tasks);
 
-            println!("\n{} The following task was added:\n", " ".green());
-            print_task(&task, false);  // Pretty-printing task
+        if is_machine() {
+            emit_list(&tasks);
+            return Ok(());
+        }
+
+        if tasks.is_empty() && filter.is_active() {
+            println!("\n{} {}", "".yellow(), "No tasks match the given filters.".yellow());
+        } else if tasks.is_empty() {
+            // If no tasks are present, tell the user instead of printing an empty task list
+            println!("\n{} {} {} {} {} {}", "  ".bold(), "No tasks have been created. Run".yellow(), "cues add".blue(), "to add new tasks, or".yellow(), "cues new project".blue(), "to create a new project.".yellow());
         } else {
-            println!();
-            log_err(res);
+            // Else, print the tasks grouped by project
+            if !projects.is_empty() {
+                println!("\n{} Available tasks:\n", " ".green());
+
+                for project in projects.iter() {
+                    // Get tasks under this project member
+                    let proj_tasks: Vec<&Task> = tasks.iter().filter(|t| t.project_id == project.id).collect();
+
+                    // If no tasks in this project, skip to next project
+                    if proj_tasks.is_empty() {
+                        continue;
+                    }
+
+                    // Else, print the project name and then list the tasks (all pretty-printed)
+
+                    println!("{} {}\n", " ".bold().yellow(), project.name.bold().yellow());
+
+                    for task in proj_tasks {
+                        // println!("Task: {:#?}", &task);
+                        print_task(task, false)?;
+                    }
+
+                    println!();
+                }
+            } else {
+                println!("\n{} {} {} {}", "  ".bold(), "No projects are defined. Run".yellow(), "cues new project".blue(), "to add a new project.".yellow());
+            }
         }
+        
     } else {
-        println!("\n{} You need to log in first to run this command. Run {} to log in to your account.", " ".red(), "cues login".yellow());
+        // For 'cues tasks' (No --all or -a flag)
+
+        // API call (get tasks)
+        // [No concurrency here as just one API call is made]
+        let mut tasks: Vec<Task> = client.list_tasks().await?
+            .into_iter()
+            .filter(|t| t.project_id == pid && matcher.matches(t))
+            .collect();
+        order.apply(&mut tasks);
+
+        if is_machine() {
+            emit_list(&tasks);
+            return Ok(());
+        }
+
+        if tasks.is_empty() && filter.is_active() {
+            println!("\n{} {}", "".yellow(), "No tasks match the given filters.".yellow());
+        } else if tasks.is_empty() {
+            // If no tasks are present, tell the user instead of printing an empty list
+            println!("\n{} {} {} {} {} {}", "  ".bold(), "No tasks present in the current project. Run".yellow(), "cues add".blue(), "to add new tasks, or".yellow(), "cues use".blue(), "to use a different project.".yellow());
+        } else {
+            // Else, pretty-print all the tasks in the CWP
+            println!("\n{} Available tasks:\n", " ".green());
+
+            for task in tasks.iter() {
+                print_task(task, false)?;
+            }
+        }
     }
 
     Ok(())
 }
 
-// HANDLER: List tasks (in cwp)
-async fn handle_list_tasks (all: bool, auth_store: &AuthStore) -> Result<(), Box<dyn error::Error>> {
-    if let Some(config) = load_config() {
-        // Get token from keyring
-        let mut token = match auth_store.access.get_password() {
-            Ok(t) => t,
-            Err(_) => {
-                println!("\n{} You may not be logged in. Run {}", " ".red(), "cues login".yellow());
-                exit(0);
-            }
-        };
+// HANDLER: Show one task in full
+async fn handle_task_show (id: u32, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    // API call
+    let task = client.get_task(id).await?;
 
-        // Check if token is expired, if yes then refresh and get a new token
-        if jwt_expired(config.expires_at.as_str()) {
-            let refresh_token = match auth_store.refresh.get_password() {
-                Ok(t) => t,
-                Err(_) => {
-                    println!("\n{} Refresh token couldn't be found. Log in again by running {}", " ".red(), "cues login".yellow());
-                    exit(0);
-                }
-            };
+    if is_machine() {
+        emit(&task);
+        return Ok(());
+    }
 
-            let new_token: String = refresh_access_token(&refresh_token).await?;
+    // The project name is only for display, so show the id alone if it can't be fetched
+    let project = client.get_project(task.project_id).await.ok();
 
-            auth_store.access.set_password(&new_token);
-            token = new_token;
-        }
+    println!();
+    print_task_detail(&task, project.as_ref())?;
 
-        let pid = config.current_project_id;  // Get current project id from config
+    Ok(())
+}
 
-        // For 'cues tasks --all'
-        if all {
-            // Concurrently make two API calls
-            let (task_res, proj_res) = tokio::join!(
-                get_tasks(token.as_str()),      // API call (Get tasks)
-                get_projects(token.as_str()),   // API call (Get projects)
-            );
+// What a status command does to each task
+#[derive(Clone, Copy)]
+enum StatusChange {
+    Done,
+    Undone,
+    Toggle,
+}
 
-            let task_res = task_res?;
-            let proj_res = proj_res?;
+// HANDLER: Mark tasks as done, reopen them, or toggle them
+async fn handle_task_status (selection: TaskSelection, change: StatusChange, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    let Some(ids) = select_tasks(&selection, client).await? else {
+        return Ok(());
+    };
 
-            // Extracting data from both responses
+    let (before, failed) = fetch_tasks(&ids, client).await?;
+    let found: Vec<u32> = before.iter().map(|t| t.id).collect();
 
-            let Some(tv) = task_res.get("tasks") else {
-                println!();
-                log_err(task_res);
-                return Ok(());
-            };
+    // API calls (concurrent)
+    let mut results = run_bulk(&found, |id| {
+        let was_done = before.iter().any(|t| t.id == id && t.is_done);
+        set_status(id, change, was_done, client)
+    }).await;
 
-            let Some(pv) = proj_res.get("projects") else {
-                println!();
-                log_err(proj_res);
-                return Ok(());
-            };
+    let op = match change {
+        StatusChange::Done => Operation::Done,
+        StatusChange::Undone => Operation::Undone,
+        StatusChange::Toggle => Operation::Toggle,
+    };
+    record_changes(op, before, &results);
+    results.extend(failed);
 
-            let tasks: Vec<Task> = serde_json::from_value(tv.clone())?;
+    let heading = match change {
+        StatusChange::Done => "Marked following task(s) as done:",
+        StatusChange::Undone => "Reopened following task(s):",
+        StatusChange::Toggle => "Toggled following task(s):",
+    };
 
-            if tasks.is_empty() {
-                // If no tasks are present, tell the user instead of printing an empty task list
-                println!("\n{} {} {} {} {} {}", "  ".bold(), "No tasks have been created. Run".yellow(), "cues add".blue(), "to add new tasks, or".yellow(), "cues new project".blue(), "to create a new project.");
-            } else {
-                // Else, print the tasks grouped by project
-                let projects: Vec<Project> = serde_json::from_value(pv.clone())?;
+    if report_results(heading, &results)? {
+        println!("\nRun {} to view all tasks in current project.", "cues tasks".yellow());
+    }
 
-                if !projects.is_empty() {
-                    println!("\n{} Available tasks:\n", " ".green());
+    Ok(())
+}
 
-                    for project in projects.iter() {
-                        // Get tasks under this project member
-                        let proj_tasks: Vec<&Task> = tasks.iter().filter(|t| t.project_id == project.id).collect();
+// HANDLER: Edit tasks. For description, priority and due date, Some(None) removes the field
+async fn handle_task_edit (selection: TaskSelection, title: Option<String>, desc: Option<Option<String>>, priority: Option<Option<PriorityType>>, due: Option<Option<String>>, done: Option<bool>, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    // Parse given format ("today 16:00" or "friday 4:00" etc) to a valid datetime format
+    let parsed_due = match due {
+        Some(Some(due_date)) => match natural_to_datetime(&due_date) {
+            Some(due_str) => Some(Some(due_str)),
+            None => {
+                log_failure("Invalid due date format.".to_string());
+                return Ok(());
+            }
+        },
+        cleared => cleared,
+    };
 
-                        // If no tasks in this project, skip to next project
-                        if proj_tasks.is_empty() {
-                            continue;
-                        }
+    // Build the payload one-by-one based on data provided by user
 
-                        // Else, print the project name and then list the tasks (all pretty-printed)
+    let mut payload = json!({});
 
-                        println!("{} {}\n", " ".bold().yellow(), project.name.bold().yellow());
+    if let Some(t) = title {
+        payload["title"] = json!(t);
+    }
 
-                        for task in proj_tasks {
-                            // println!("Task: {:#?}", &task);
-                            print_task(task, false);
-                        }
+    // Cleared fields are sent as null
+    if let Some(d) = desc {
+        payload["description"] = json!(d);
+    }
 
-                        println!();
-                    }
-                } else {
-                    println!("\n{} {} {} {}", "  ".bold(), "No projects are defined. Run".yellow(), "cues new project".blue(), "to add a new project.".yellow());
-                }
-            }
-            
-        } else {
-            // For 'cues tasks' (No --all or -a flag)
+    if let Some(p) = priority {
+        payload["priority"] = json!(p.map(|p| format!("{:?}", p)));
+    }
 
-            // API call (get tasks)
-            // [No concurrency here as just one API call is made]
-            let task_res = get_tasks(token.as_str()).await?;
+    if let Some(due_str) = parsed_due {
+        payload["due"] = json!(due_str);
+    }
 
-            // Extracting data from response
-            let Some(tv) = task_res.get("tasks") else {
-                println!();
-                log_err(task_res);
-                return Ok(());
-            };
+    if let Some(done_status) = done {
+        payload["isDone"] = json!(done_status);
+    }
 
-            let tasks: Vec<Task> = serde_json::from_value(tv.clone())?;
+    // Nothing would change, so there's no point in touching (or selecting) any task
+    if payload.as_object().is_some_and(|fields| fields.is_empty()) {
+        log_failure(format!("Nothing to change. Give the fields to edit (eg, {} or {}).", "--title".yellow(), "--no-due".yellow()));
+        return Ok(());
+    }
 
-            if tasks.is_empty() {
-                // If no tasks are present, tell the user instead of printing an empty list
-                println!("\n{} {} {} {} {} {}", "  ".bold(), "No tasks present in the current project. Run".yellow(), "cues add".blue(), "to add new tasks, or".yellow(), "cues use".blue(), "to use a different project.".yellow());
-            } else {
-                // Else, pretty-print all the tasks in the CWP
-                println!("\n{} Available tasks:\n", " ".green());
+    let Some(ids) = select_tasks(&selection, client).await? else {
+        return Ok(());
+    };
 
-                for task in tasks.iter().filter(|t| t.project_id == pid) {
-                    print_task(&task, false);
-                }
-            }
-        }
-    } else {
-        println!("\n{} You need to log in first to run this command. Run {} to log in to your account.", " ".red(), "cues login".yellow());
-    }
+    let (before, failed) = fetch_tasks(&ids, client).await?;
+    let found: Vec<u32> = before.iter().map(|t| t.id).collect();
 
-    Ok(())
-}
+    // API calls (concurrent, same changes for every task)
+    let mut results = run_bulk(&found, |id| client.update_task(id, &payload)).await;
 
-// HANDLER: Mark task as done
-async fn handle_task_done (id: u32, auth_store: &AuthStore) -> Result<(), Box<dyn error::Error>> {
-    if let Some(config) = load_config() {
-        // Get token from keyring
-        let mut token = match auth_store.access.get_password() {
-            Ok(t) => t,
-            Err(_) => {
-                println!("\n{} You may not be logged in. Run {}", " ".red(), "cues login".yellow());
-                exit(0);
-            }
-        };
+    record_changes(Operation::Edit, before, &results);
+    results.extend(failed);
 
-        // Check if token is expired, if yes then refresh and get a new token
-        if jwt_expired(config.expires_at.as_str()) {
-            let refresh_token = match auth_store.refresh.get_password() {
-                Ok(t) => t,
-                Err(_) => {
-                    println!("\n{} Refresh token couldn't be found. Log in again by running {}", " ".red(), "cues login".yellow());
-                    exit(0);
-                }
-            };
+    if report_results("Following task(s) have been updated:", &results)? {
+        println!("\nRun {} to view all tasks in current project.", "cues tasks".yellow());
+    }
 
-            let new_token = refresh_access_token(&refresh_token).await?;
+    Ok(())
+}
 
-            auth_store.access.set_password(&new_token);
-            token = new_token;
-        }
+// HANDLER: Move tasks to another project
+async fn handle_task_move (selection: TaskSelection, to: String, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    // Make sure the target project exists before touching any task
+    let target = resolve_project(client, &to).await?;
 
-        let payload = json!({
-            "isDone": true,
-        });
+    let Some(ids) = select_tasks(&selection, client).await? else {
+        return Ok(());
+    };
 
-        // API call
-        let res = put_task(token.as_str(), &id, &payload).await?;
+    let (before, failed) = fetch_tasks(&ids, client).await?;
+    let found: Vec<u32> = before.iter().map(|t| t.id).collect();
 
-        // Extracting data from response
-        if let Some(taskval) = res.get("task") {
-            let task: Task = serde_json::from_value(taskval.clone())?;
+    // API calls (concurrent)
+    let payload = json!({ "projectId": target.id });
+    let mut results = run_bulk(&found, |id| client.update_task(id, &payload)).await;
 
-            println!("\n{}", " Marked following task as done:\n".green());
-            print_task(&task, false);  // Pretty-print task marked as Done
+    record_changes(Operation::Move, before, &results);
+    results.extend(failed);
 
-            println!("\nRun {} to view all tasks in current project.", "cues tasks".yellow());
-        } else {
-            println!();
-            log_err(res);
-        }
-    } else {
-        println!("\n{} You need to log in first to run this command. Run {} to log in to your account.", " ".red(), "cues login".yellow());
+    let heading = format!("Moved following task(s) to [{}] {}:", target.id, target.name);
+    if report_results(&heading, &results)? {
+        println!("\nRun {} to view the tasks in that project.", format!("cues tasks -p {}", target.id).yellow());
     }
 
     Ok(())
 }
 
-// HANDLER: Edit task
-async fn handle_task_edit (id: u32, title: Option<String>, desc: Option<String>, priority: Option<PriorityType>, due: Option<String>, done: Option<bool>, auth_store: &AuthStore) -> Result<(), Box<dyn error::Error>> {
-    if let Some(config) = load_config() {
-        // Get token from keyring
-        let mut token = match auth_store.access.get_password() {
-            Ok(t) => t,
-            Err(_) => {
-                println!("\n{} You may not be logged in. Run {}", " ".red(), "cues login".yellow());
-                exit(0);
-            }
-        };
+// HANDLER: Edit every field of a task in $VISUAL/$EDITOR
+async fn handle_task_edit_interactive (id: u32, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    let task = client.get_task(id).await?;
+    let original = TaskDraft::from_task(&task);
 
-        // Check if token is expired, if yes then refresh and get a new token
-        if jwt_expired(config.expires_at.as_str()) {
-            let refresh_token = match auth_store.refresh.get_password() {
-                Ok(t) => t,
-                Err(_) => {
-                    println!("\n{} Refresh token couldn't be found. Log in again by running {}", " ".red(), "cues login".yellow());
-                    exit(0);
-                }
-            };
+    let Some(edited) = edit_draft(&original, &format!("task-{}", id))? else {
+        return Ok(());
+    };
 
-            let new_token = refresh_access_token(&refresh_token).await?;
+    // Only the fields that were changed are sent, removed ones as null
+    let mut payload = json!({});
 
-            auth_store.access.set_password(&new_token);
-            token = new_token;
-        }
+    if edited.title != original.title {
+        payload["title"] = json!(edited.title);
+    }
 
-        // Parse given format ("today 16:00" or "friday 4:00" etc) to a valid datetime format
-        let parsed_due = if let Some(due_date) = due {
-            match natural_to_datetime(&due_date) {
-                Some(due_str) => Some(due_str),
-                None => {
-                    eprintln!("{} Invalid due date format.", " ".red());
-                    return Ok(());
-                }
-            }
-        } else {
-            None
-        };
+    if edited.priority != original.priority {
+        payload["priority"] = json!(edited.priority.map(|p| format!("{:?}", p)));
+    }
 
-        // Build the payload one-by-one based on data provided by user
+    if edited.due != original.due {
+        payload["due"] = json!(edited.due.as_deref().and_then(natural_to_datetime));
+    }
 
-        let mut payload = json!({});
+    if edited.description != original.description {
+        payload["description"] = json!(edited.description);
+    }
 
-        if let Some(t) = title {
-            payload["title"] = json!(t);
+    if payload.as_object().is_some_and(|fields| fields.is_empty()) {
+        if is_machine() {
+            emit(&task);
+            return Ok(());
         }
 
-        if let Some(d) = desc {
-            payload["description"] = json!(d);
-        }
+        println!("\n{} No changes were made.", "".yellow());
+        return Ok(());
+    }
 
-        if let Some(p) = priority {
-            payload["priority"] = json!(format!("{:?}", p));
-        }
+    // API call
+    let updated = client.update_task(id, &payload).await?;
+    record(Operation::Edit, vec![task]);
 
-        if let Some(due_str) = parsed_due {
-            payload["due"] = json!(due_str);
-        }
+    if is_machine() {
+        emit(&updated);
+        return Ok(());
+    }
 
-        if let Some(done_status) = done {
-            payload["isDone"] = json!(done_status);
-        }
+    println!("\n{}", " Following task has been updated:\n".green());
+    print_task(&updated, false)?;
 
-        // API call
-        let res = put_task(token.as_str(), &id, &payload).await?;
+    Ok(())
+}
 
-        // Extracting data from response
-        if let Some(taskval) = res.get("task") {
-            let task: Task = serde_json::from_value(taskval.clone())?;
+// HANDLER: Delete tasks (after showing them and asking, unless --yes)
+async fn handle_task_delete (selection: TaskSelection, yes: bool, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    let Some(ids) = select_tasks(&selection, client).await? else {
+        return Ok(());
+    };
 
-            println!("\n{}", " Following task has been updated:\n".green());
-            print_task(&task, false);  // Pretty-print the edited task
+    // Tasks that couldn't be fetched are reported without trying to delete them
+    let (before, failed) = fetch_tasks(&ids, client).await?;
+    let found: Vec<u32> = before.iter().map(|t| t.id).collect();
 
-            println!("\nRun {} to view all tasks in current project.", "cues tasks".yellow());
-        } else {
-            println!();
-            log_err(res);
+    if !yes && !is_dry_run() && !before.is_empty() {
+        // Show what is about to go, so a mistyped id doesn't silently remove the wrong task
+        if !is_machine() {
+            println!("\n{} {}\n", "".yellow(), "Following task(s) will be deleted:".yellow());
+            for task in &before {
+                print_task(task, false)?;
+            }
         }
-    } else {
-        println!("\n{} You need to log in first to run this command. Run {} to log in to your account.", " ".red(), "cues login".yellow());
+
+        if !confirm(&format!("Delete {} task(s)?", before.len()))? {
+            report_cancelled();
+            return Ok(());
+        }
+    }
+
+    // API calls (concurrent)
+    let mut results = run_bulk(&found, |id| client.delete_task(id)).await;
+
+    record_changes(Operation::Delete, before, &results);
+    results.extend(failed);
+
+    if report_results("Following task(s) have been deleted:", &results)? {
+        println!("\nRun {} to view all available tasks in current project.", "cues tasks".yellow());
     }
 
     Ok(())
 }
 
-// HANDLER: Delete task
-async fn handle_task_delete (id: u32, auth_store: &AuthStore) -> Result<(), Box<dyn error::Error>> {
-    if let Some(config) = load_config() {
-        // Get token from keyring
-        let mut token = match auth_store.access.get_password() {
-            Ok(t) => t,
-            Err(_) => {
-                println!("\n{} You may not be logged in. Run {}", " ".red(), "cues login".yellow());
-                exit(0);
-            }
-        };
+// HANDLER: Revert the latest add, edit, status change, move or delete
+async fn handle_undo (client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    let Some(entry) = load_history().pop() else {
+        log_failure("Nothing to undo.".to_string());
+        return Ok(());
+    };
 
-        // Check if token is expired, if yes then refresh and get a new token
-        if jwt_expired(config.expires_at.as_str()) {
-            let refresh_token = match auth_store.refresh.get_password() {
-                Ok(t) => t,
-                Err(_) => {
-                    println!("\n{} Refresh token couldn't be found. Log in again by running {}", " ".red(), "cues login".yellow());
-                    exit(0);
-                }
-            };
+    let ids: Vec<u32> = entry.tasks.iter().map(|t| t.id).collect();
 
-            let new_token_res: String = refresh_access_token(&refresh_token).await?;
+    // API calls (concurrent)
+    let results = match entry.op {
+        Operation::Add => run_bulk(&ids, |id| client.delete_task(id)).await,
+        Operation::Delete => run_bulk(&ids, |id| recreate_task(id, &entry.tasks, client)).await,
+        _ => run_bulk(&ids, |id| restore_task(id, &entry.tasks, client)).await,
+    };
 
-            auth_store.access.set_password(&new_token_res)?;
-            token = new_token_res;
-        }
+    // Keep what couldn't be undone for another try. Tasks that are gone can't be brought back by retrying
+    if !is_dry_run() {
+        let left: Vec<Task> = entry.tasks.iter()
+            .filter(|t| results.iter().any(|(id, res)| *id == t.id && matches!(res, Err(e) if !matches!(e, ApiError::NotFound(_)))))
+            .cloned()
+            .collect();
 
-        // API call
-        let res = delete_task(token.as_str(), &id).await?;
+        let remaining = (!left.is_empty()).then(|| HistoryEntry { tasks: left, ..entry.clone() });
 
-        // Extracting data from response
-        if let Some(taskval) = res.get("task") {
-            let task: Task = serde_json::from_value(taskval.clone())?;
+        // Recreated tasks have new ids, which older operations should use from now on
+        let moved: Vec<(u32, u32)> = match entry.op {
+            Operation::Delete => results.iter().filter_map(|(id, res)| res.as_ref().ok().map(|t| (*id, t.id))).collect(),
+            _ => Vec::new(),
+        };
 
-            println!("\n{}", " Following task has been deleted:\n".green());
-            print_task(&task, false);  // Pretty-print the deleted task
+        settle_last(remaining, &moved)?;
+    }
 
-            println!("\nRun {} to view all available tasks in current project.", "cues tasks".yellow());
-        } else {
-            println!();
-            log_err(res);
-        }
-    } else {
-        println!("\n{} You need to log in first to run this command. Run {} to log in to your account.", " ".red(), "cues login".yellow());
+    let heading = match entry.op {
+        Operation::Add => "Undid add, following task(s) have been deleted:".to_string(),
+        Operation::Delete => "Undid delete, following task(s) have been recreated (with new ids):".to_string(),
+        op => format!("Undid {}, following task(s) have been restored:", op.label()),
+    };
+
+    if report_results(&heading, &results)? {
+        println!("\nRun {} to see what else can be undone.", "cues history".yellow());
     }
 
     Ok(())
 }
 
-// HANDLER: Get user details (whoami)
-async fn whoami (auth_store: &AuthStore) -> Result<(), Box<dyn error::Error>> {
-    if let Some(config) = load_config() {
-        // Get token from keyring
-        let mut token: String = match auth_store.access.get_password() {
-            Ok(t) => t,
-            Err(_) => {
-                println!("\n{} You may not be logged in. Run {}", " ".red(), "cues login".yellow());
-                exit(0);
-            }
-        };
+// HANDLER: List recent operations of this profile, latest first
+fn handle_history (limit: usize) -> Result<(), Box<dyn error::Error>> {
+    let entries: Vec<HistoryEntry> = load_history().into_iter().rev().take(limit).collect();
 
-        // Check if token is expired, if yes then refresh and get a new token
-        if jwt_expired(config.expires_at.as_str()) {
-            let refresh_token = match auth_store.refresh.get_password() {
-                Ok(t) => t,
-                Err(_) => {
-                    println!("\n{} Refresh token couldn't be found. Log in again by running {}", " ".red(), "cues login".yellow());
-                    exit(0);
-                }
-            };
+    if is_machine() {
+        emit_list(&entries);
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        println!("\n{} Nothing to undo yet.", "".yellow());
+        return Ok(());
+    }
 
-            let new_token_res: String = refresh_access_token(&refresh_token).await?;
+    println!("\nRecent operations ({} reverts the first one):\n", "cues undo".yellow());
+    for (i, entry) in entries.iter().enumerate() {
+        let when = DateTime::parse_from_rfc3339(&entry.at)
+            .map(|at| at.with_timezone(&Local).format("%d %b %Y, %H:%M").to_string())
+            .unwrap_or_else(|_| entry.at.clone());
 
-            auth_store.access.set_password(&new_token_res)?;
-            token = new_token_res;
+        println!("{} {} {} {}", format!("[{}]", i + 1).yellow(), format!("{:<7}", entry.op.label()).bold(), when.dimmed(), format!("({} task(s))", entry.tasks.len()).dimmed());
+        for task in &entry.tasks {
+            println!("      {} {}", format!("[{}]", task.id).yellow(), task.title);
         }
+    }
 
-        // API call
-        let res = get_user(token.as_str()).await?;
+    Ok(())
+}
 
-        // Extracting data from response
+// HANDLER: Get user details (whoami)
+async fn whoami (client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    // API call
+    let user = client.get_user().await?;
 
-        let Some(userval) = res.get("user") else {
-            println!();
-            log_err(res);
-            return Ok(());
-        };
+    if is_machine() {
+        emit(&user);
+        return Ok(());
+    }
 
-        let user: User = serde_json::from_value(userval.clone())?;
-        let user_joined_date = format_pretty_date(user.created_at.as_str())?;
+    let user_joined_date = format_pretty_date(user.created_at.as_str())?;
 
-        // Displaying user information
+    // Displaying user information
 
-        let cues_ascii: &str = r#"
-        
+    let cues_ascii: &str = r#"
+    
 
  ██████╗██╗   ██╗███████╗███████╗
 ██╔════╝██║   ██║██╔════╝██╔════╝
@@ -646,31 +749,36 @@ async fn whoami (auth_store: &AuthStore) -> Result<(), Box<dyn error::Error>> {
 ██║     ██║   ██║██╔══╝  ╚════██║
 ╚██████╗╚██████╔╝███████╗███████║
  ╚═════╝ ╚═════╝ ╚══════╝╚══════╝
-                                 
-        "#;
+                             
+    "#;
 
-        println!("{}", cues_ascii.yellow());
-        println!("\n{}", "  User Information".yellow());
-        println!("──────────────────────────────");
-        println!("\n\n{} {}", "  Username:".blue(), user.username);
-        println!("\n{} {}", "󰇮  Email address:".blue(), user.email);
-        println!("\n{} {}", "  Joined on:".blue(), user_joined_date);
-    } else {
-        println!("\n{} You need to log in first to run this command. Run {} to log in to your account.", " ".red(), "cues login".yellow());
-    }
+    println!("{}", cues_ascii.yellow());
+    println!("\n{}", "  User Information".yellow());
+    println!("──────────────────────────────");
+    println!("\n\n{} {}", "  Username:".blue(), user.username);
+    println!("\n{} {}", "󰇮  Email address:".blue(), user.email);
+    println!("\n{} {}", "  Joined on:".blue(), user_joined_date);
+    println!("\n{} {}", "  Profile:".blue(), active_profile());
 
     Ok(())
 }
 
 // HANDLER: Log in
-async fn handle_login (auth_store: &AuthStore) -> Result<(), Box<dyn error::Error>> {
-     let config_path = get_config_path().expect("Could not determine config directory");
-
-     if let Some(parent) = config_path.parent() {
-         fs::create_dir_all(parent)?;
-     }
+async fn handle_login (username: Option<String>, password_stdin: bool, auth_store: &AuthStore, client: &CuesClient) -> Result<(), Box<dyn error::Error>> {
+    // Credentials may be given up front (flags or environment) for non-interactive use
+    let username = username.or_else(|| env_var("CUES_USERNAME"));
+    let password = if password_stdin {
+        let mut pswd = String::new();
+        io::stdin().read_line(&mut pswd)?;
+        Some(pswd.trim_end_matches(['\r', '\n']).to_string())
+    } else {
+        env_var("CUES_PASSWORD")
+    };
 
-    let cues_ascii: &str = r#"
+    // Only show the banner when prompting the user
+    let interactive = username.is_none() || password.is_none();
+    if interactive && !is_machine() {
+        let cues_ascii: &str = r#"
         
 
  ██████╗██╗   ██╗███████╗███████╗
@@ -683,69 +791,312 @@ async fn handle_login (auth_store: &AuthStore) -> Result<(), Box<dyn error::Erro
 
     "#;
 
-    println!("{}", cues_ascii.yellow());
-
-    println!("\n{}", "󰍂 Log in to Cues CLI".yellow());
-    println!("──────────────────────────────");
+        println!("{}", cues_ascii.yellow());
 
-    print!("\n\n{} {} ", " ".blue(), "Username or Email:".bold());
-    io::stdout().flush()?;
-    let mut username_or_email = String::new();
-    io::stdin().read_line(&mut username_or_email)?;
-    let username_or_email = username_or_email.trim();
+        println!("\n{}", "󰍂 Log in to Cues CLI".yellow());
+        println!("──────────────────────────────");
+    }
 
-    print!("\n{} {} ", " ".blue(), "Password:".bold());
-    io::stdout().flush()?;
-    let pswd = read_password()?.trim().to_string();
+    // Fall back to a file in the config directory when the OS keyring can't be used
+    let fallback;
+    let auth_store = if auth_store.kind() == CredentialStore::Keyring && !keyring_available() {
+        let choice = if interactive { choose_fallback_store()? } else { None };
 
-    let mut payload = json!({
-        "password": pswd,
-    });
+        let Some(kind) = choice else {
+            log_failure(format!("The system keyring is unavailable. Log in with {} (or {}) to keep credentials in the config directory.", "--credential-store encrypted-file".yellow(), "file".yellow()));
+            return Ok(());
+        };
 
-    if username_or_email.contains("@") {
-        payload["email"] = json!(username_or_email);
+        fallback = auth_store.with_kind(kind);
+        &fallback
     } else {
-        payload["username"] = json!(username_or_email);
-    }
-
-    let res = post_login(&payload).await?;
-
-    let Some(tokenval) = res.get("accessToken").and_then(|val| val.as_str()) else {
-        println!();
-        log_err(res);
-        return Ok(());
+        auth_store
     };
 
-    let Some(refreshval) = res.get("refreshToken").and_then(|val| val.as_str()) else {
-        println!();
-        log_err(res);
-        return Ok(());
+    let username_or_email = match username {
+        Some(u) => u,
+        None => {
+            print!("\n\n{} {} ", " ".blue(), "Username or Email:".bold());
+            io::stdout().flush()?;
+            let mut username_or_email = String::new();
+            io::stdin().read_line(&mut username_or_email)?;
+            username_or_email.trim().to_string()
+        }
     };
 
-    let access_token = tokenval.to_string();
-    let refresh_token = refreshval.to_string();
+    let pswd = match password {
+        Some(p) => p,
+        None => {
+            print!("\n{} {} ", " ".blue(), "Password:".bold());
+            io::stdout().flush()?;
+            read_password()?.trim().to_string()
+        }
+    };
 
-    let expires_at = (Local::now() + Duration::hours(1)).to_rfc3339();
+    let tokens = client.login(&username_or_email, &pswd).await?;
 
-    auth_store.access.set_password(&access_token)?;
-    auth_store.refresh.set_password(&refresh_token)?;
+    let expires_at = token_expiry(&tokens);
+    let access_token = tokens.access_token;
+    let Some(refresh_token) = tokens.refresh_token else {
+        log_failure("The server did not return a refresh token.".to_string());
+        return Ok(());
+    };
 
+    auth_store.set(ACCESS_TOKEN, &access_token)?;
+    auth_store.set(REFRESH_TOKEN, &refresh_token)?;
 
+    // Keep the configured backend and preferences across logins, and remember where the tokens went
+    let previous = load_config().unwrap_or_default();
     let config = Config {
-        expires_at: expires_at,
+        expires_at,
         current_project: String::new(),
         current_project_id: 0,
+        credential_store: Some(auth_store.kind()).filter(|k| *k != CredentialStore::Keyring),
+        ..previous
     };
 
+    save_config(&config)?;
 
-    let json = serde_json::to_string_pretty(&config)?;
-    fs::write(&config_path, json)?;
+    if is_machine() {
+        emit(&json!({ "username": username_or_email, "profile": active_profile() }));
+        return Ok(());
+    }
 
-    println!("\n\n{}{}{}.", "  ".green(), "Logged in succesfully, as ", username_or_email.yellow());
+    println!("\n\n{}Logged in succesfully, as {}.", "  ".green(), username_or_email.yellow());
 
     Ok(())
 }
 
+// HELPER: Ids of the tasks picked on the command line. Reports and returns None when there's nothing to do
+async fn select_tasks (selection: &TaskSelection, client: &CuesClient) -> Result<Option<Vec<u32>>, ApiError> {
+    if selection.is_empty() {
+        log_failure(format!("Give task ids (eg, {}) or filters (eg, {}) to select tasks.", "4 7 10-14".yellow(), "--done".yellow()));
+        return Ok(None);
+    }
+
+    let selection = match selection.clone().with_criteria() {
+        Ok(selection) => selection,
+        Err(msg) => {
+            log_failure(msg);
+            return Ok(None);
+        },
+    };
+
+    let ids = selection.resolve(client).await?;
+
+    if ids.is_empty() {
+        if is_machine() {
+            emit_list::<Task>(&[]);
+        } else {
+            println!("\n{} {}", "".yellow(), "No tasks match the given filters.".yellow());
+        }
+        return Ok(None);
+    }
+
+    Ok(Some(ids))
+}
+
+// HELPER: Fetch tasks before they are changed, for the undo history. Ids missing from the task list come back as NotFound
+async fn fetch_tasks (ids: &[u32], client: &CuesClient) -> Result<(Vec<Task>, Vec<(u32, Result<Task, ApiError>)>), ApiError> {
+    // One listing for every id (the backend has no endpoint for a single task)
+    let tasks = client.list_tasks().await?;
+
+    let mut found = Vec::new();
+    let mut failed = Vec::new();
+
+    for &id in ids {
+        match tasks.iter().find(|t| t.id == id) {
+            Some(task) => found.push(task.clone()),
+            None => failed.push((id, Err(ApiError::NotFound(Some(format!("Task {} not found", id)))))),
+        }
+    }
+
+    Ok((found, failed))
+}
+
+// HELPER: Record the tasks that were changed, as they were before, so `cues undo` can revert them
+fn record_changes (op: Operation, before: Vec<Task>, results: &[(u32, Result<Task, ApiError>)]) {
+    let changed = before.into_iter()
+        .filter(|t| results.iter().any(|(id, res)| *id == t.id && res.is_ok()))
+        .collect();
+
+    record(op, changed);
+}
+
+// HELPER: Put back every field of a task as recorded in the history
+async fn restore_task (id: u32, tasks: &[Task], client: &CuesClient) -> Result<Task, ApiError> {
+    let Some(task) = tasks.iter().find(|t| t.id == id) else {
+        return Err(ApiError::NotFound(None));
+    };
+
+    client.update_task(id, &restore_payload(task)).await
+}
+
+// HELPER: Create a deleted task again (the backend gives it a new id)
+async fn recreate_task (id: u32, tasks: &[Task], client: &CuesClient) -> Result<Task, ApiError> {
+    let Some(task) = tasks.iter().find(|t| t.id == id) else {
+        return Err(ApiError::NotFound(None));
+    };
+
+    let mut payload = restore_payload(task);
+    if let Some(fields) = payload.as_object_mut() {
+        fields.remove("isDone");
+        fields.retain(|_, value| !value.is_null());
+    }
+
+    let created = client.create_task(&payload).await?;
+
+    // New tasks always start out pending
+    if task.is_done {
+        return client.update_task(created.id, &json!({ "isDone": true })).await;
+    }
+
+    Ok(created)
+}
+
+// HELPER: Apply a status change to one task
+async fn set_status (id: u32, change: StatusChange, was_done: bool, client: &CuesClient) -> Result<Task, ApiError> {
+    let done = match change {
+        StatusChange::Done => true,
+        StatusChange::Undone => false,
+        StatusChange::Toggle => !was_done,
+    };
+
+    // API call
+    client.update_task(id, &json!({ "isDone": done })).await
+}
+
+// HELPER: Report the outcome of a command run on several tasks, one line per failure.
+// Returns whether anything was printed for people (so callers can add a closing hint)
+fn report_results (heading: &str, results: &[(u32, Result<Task, ApiError>)]) -> Result<bool, Box<dyn error::Error>> {
+    // Requests held back by --dry-run were printed already, they neither succeeded nor failed
+    let results: Vec<&(u32, Result<Task, ApiError>)> = results.iter().filter(|(_, res)| !matches!(res, Err(ApiError::DryRun))).collect();
+    if results.is_empty() {
+        return Ok(false);
+    }
+
+    let failures: Vec<(u32, &ApiError)> = results.iter().filter_map(|(id, res)| res.as_ref().err().map(|e| (*id, e))).collect();
+
+    if !failures.is_empty() {
+        mark_failed();
+    }
+
+    if is_machine() {
+        let out: Vec<_> = results.iter().map(|(id, res)| match res {
+            Ok(task) => json!({ "id": id, "ok": true, "task": task }),
+            Err(e) => json!({ "id": id, "ok": false, "error": { "kind": e.kind(), "message": e.to_string() } }),
+        }).collect();
+
+        emit_list(&out);
+        return Ok(false);
+    }
+
+    // A single task that failed is reported like any other error, with its hint
+    if let [(_, Err(e))] = results.as_slice() {
+        println!();
+        log_err(e);
+        return Ok(false);
+    }
+
+    let succeeded: Vec<&Task> = results.iter().filter_map(|(_, res)| res.as_ref().ok()).collect();
+    if !succeeded.is_empty() {
+        println!("\n{} {}\n", "".green(), heading.green());
+        for task in succeeded {
+            print_task(task, false)?;
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\n{} {}\n", "".red(), format!("Failed for {} task(s):", failures.len()).red());
+        for (id, e) in failures.iter() {
+            println!("  {} {}", format!("[{}]", id).yellow(), e);
+        }
+    }
+
+    if results.len() > 1 {
+        let summary = format!("{} of {} task(s) succeeded, {} failed.", results.len() - failures.len(), results.len(), failures.len());
+        println!("\n{}", if failures.is_empty() { summary.green() } else { summary.yellow() });
+    }
+
+    Ok(true)
+}
+
+// HELPER: Ask a yes/no question, defaulting to no
+fn confirm (prompt: &str) -> io::Result<bool> {
+    // Keep stdout clean for --output json
+    if is_machine() {
+        eprint!("{} [y/N] ", prompt);
+    } else {
+        print!("\n{} {} ", prompt, "[y/N]".dimmed());
+        io::stdout().flush()?;
+    }
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+// HELPER: Let the user edit a task document until it reads back without errors.
+// None when they give up, with the file left in place so nothing they wrote is lost
+fn edit_draft (draft: &TaskDraft, name: &str) -> Result<Option<TaskDraft>, Box<dyn error::Error>> {
+    let path = create_draft(name, &draft.render())?;
+
+    loop {
+        open_editor(&path)?;
+
+        let errors = match TaskDraft::parse(&fs::read_to_string(&path)?) {
+            Ok(edited) => {
+                let _ = fs::remove_file(&path);
+                return Ok(Some(edited));
+            },
+            Err(errors) => errors,
+        };
+
+        // Keep stdout clean for --output json
+        let report = format!("{} {}", "".red(), format!("Couldn't read {}:", path.display()).red());
+        let lines: Vec<String> = errors.iter().map(|e| format!("  {} {}", format!("line {}:", e.line).yellow(), e.message)).collect();
+        if is_machine() {
+            eprintln!("{}\n{}", report, lines.join("\n"));
+        } else {
+            println!("\n{}\n\n{}", report, lines.join("\n"));
+        }
+
+        if !confirm("Open the editor again to fix it?")? {
+            log_failure(format!("Nothing was saved. What you wrote is kept in {}", path.display()));
+            return Ok(None);
+        }
+    }
+}
+
+// HELPER: Tell the user that a confirmation was declined
+fn report_cancelled () {
+    if is_machine() {
+        emit_error("cancelled", "Nothing was deleted", None);
+        return;
+    }
+
+    println!("\n{} Nothing was deleted.", "".yellow());
+}
+
+// HELPER: Ask where to keep credentials when the OS keyring can't be used
+fn choose_fallback_store () -> io::Result<Option<CredentialStore>> {
+    println!("\n{} The system keyring is unavailable. Where should your credentials be stored?\n", "".yellow());
+    println!("  {} Encrypted file (protected by a passphrase)", "[1]".yellow());
+    println!("  {} Plain file (readable only by you, not encrypted)", "[2]".yellow());
+
+    print!("\nChoose 1 or 2: ");
+    io::stdout().flush()?;
+    let mut choice = String::new();
+    io::stdin().read_line(&mut choice)?;
+
+    Ok(match choice.trim() {
+        "1" => Some(CredentialStore::EncryptedFile),
+        "2" => Some(CredentialStore::File),
+        _ => None,
+    })
+}
+
 pub async fn handle_logout (auth_store: &AuthStore) -> Result<(), Box<dyn error::Error>> {
     if let Some(mut config) = load_config() {
         let cues_ascii: &str = r#"
@@ -761,14 +1112,17 @@ pub async fn handle_logout (auth_store: &AuthStore) -> Result<(), Box<dyn error:
 
         "#;
 
-        println!("{}", cues_ascii.yellow());
+        if !is_machine() {
+            println!("{}", cues_ascii.yellow());
+        }
 
-        match auth_store.access.delete_credential() {
-            Ok(_) => println!(""),
+        match auth_store.delete(ACCESS_TOKEN) {
+            Ok(_) if is_machine() => {},
+            Ok(_) => println!(),
             Err(e) => eprintln!("{} Failed to clear access token: {}", "".red(), e),
         }
 
-        match auth_store.refresh.delete_credential() {
+        match auth_store.delete(REFRESH_TOKEN) {
             Ok(_) => print!(""),
             Err(e) => eprintln!("{} Failed to clear refresh token: {}", "".red(), e),
         }
@@ -777,10 +1131,12 @@ pub async fn handle_logout (auth_store: &AuthStore) -> Result<(), Box<dyn error:
         config.current_project = String::new();
         config.current_project_id = 0;
 
-        let config_path = get_config_path().expect("Could not determine config directory");
-        let json = serde_json::to_string_pretty(&config)?;
+        save_config(&config)?;
 
-        fs::write(&config_path, json)?;
+        if is_machine() {
+            emit(&json!({ "profile": active_profile(), "loggedOut": true }));
+            return Ok(());
+        }
 
         println!("\n{} {} {}", " ".green(), "Logged out successfully. Log in using the command".green(), "cues login".yellow());
     }
@@ -788,40 +1144,279 @@ pub async fn handle_logout (auth_store: &AuthStore) -> Result<(), Box<dyn error:
     Ok(())
 }
 
+// HANDLER: Add a profile
+fn handle_profile_add (name: String, api_url: Option<String>, credential_store: Option<CredentialStore>) -> Result<(), Box<dyn error::Error>> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        log_failure(format!("Profile names may only contain letters, digits, {} and {}.", "-".yellow(), "_".yellow()));
+        return Ok(());
+    }
+
+    let mut file = load_config_file()?.unwrap_or_default();
+    if name == DEFAULT_PROFILE || file.profiles.contains_key(&name) {
+        log_failure(format!("Profile {} already exists.", name.yellow()));
+        return Ok(());
+    }
+
+    let config = Config {
+        api_url: api_url.map(|u| u.trim().trim_end_matches('/').to_string()).filter(|u| !u.is_empty()),
+        credential_store: credential_store.filter(|k| *k != CredentialStore::Keyring),
+        ..Config::default()
+    };
+
+    file.profiles.insert(name.clone(), config.clone());
+    save_config_file(&file)?;
+
+    if is_machine() {
+        emit(&json!({ "name": name, "apiUrl": config.api_url, "credentialStore": config.credential_store }));
+        return Ok(());
+    }
+
+    println!("\n{} Added profile {}. Switch to it with {} and log in with {}.", "".green(), name.yellow(), format!("cues profile use {}", name).yellow(), "cues login".yellow());
+
+    Ok(())
+}
+
+// HANDLER: Switch the active profile
+fn handle_profile_use (name: String) -> Result<(), Box<dyn error::Error>> {
+    let mut file = load_config_file()?.unwrap_or_default();
+    if name != DEFAULT_PROFILE && !file.profiles.contains_key(&name) {
+        log_failure(format!("Profile {} doesn't exist. Run {} to list profiles.", name.yellow(), "cues profile list".yellow()));
+        return Ok(());
+    }
+
+    file.active_profile = name.clone();
+    save_config_file(&file)?;
+
+    if is_machine() {
+        emit(&json!({ "activeProfile": name }));
+        return Ok(());
+    }
+
+    println!("\n{}\n{}", "  Switched to profile:".green(), name);
+
+    Ok(())
+}
+
+// HANDLER: List profiles
+fn handle_profile_list () -> Result<(), Box<dyn error::Error>> {
+    let mut file = load_config_file()?.unwrap_or_default();
+    file.profiles.entry(DEFAULT_PROFILE.to_string()).or_default();
+
+    let active = active_profile();
+
+    if is_machine() {
+        let profiles: Vec<_> = file.profiles.iter().map(|(name, config)| json!({
+            "name": name,
+            "active": *name == active,
+            "loggedIn": !config.expires_at.is_empty(),
+            "apiUrl": config.api_url,
+            "credentialStore": config.credential_store,
+            "currentProjectId": config.current_project_id,
+            "currentProject": config.current_project,
+        })).collect();
+
+        emit_list(&profiles);
+        return Ok(());
+    }
+
+    println!("\nProfiles:\n");
+    for (name, config) in file.profiles.iter() {
+        let marker = if *name == active { "".green() } else { " ".normal() };
+        let status = if config.expires_at.is_empty() { "logged out".red() } else { "logged in".green() };
+        let server = config.api_url.as_deref().unwrap_or("default server");
+
+        println!("{} {} {} {}", marker, format!("{:<12}", name).bold(), status, format!("({})", server).dimmed());
+
+        if config.current_project_id != 0 {
+            println!("  {} {} {}", "Project:".blue(), format!("[{}]", config.current_project_id).yellow(), config.current_project);
+        }
+    }
+
+    Ok(())
+}
+
+// HANDLER: Remove a profile along with its stored credentials
+fn handle_profile_remove (name: String) -> Result<(), Box<dyn error::Error>> {
+    let mut file = load_config_file()?.unwrap_or_default();
+
+    if name == DEFAULT_PROFILE {
+        log_failure(format!("The {} profile can't be removed.", DEFAULT_PROFILE.yellow()));
+        return Ok(());
+    }
+    if name == active_profile() {
+        log_failure(format!("Profile {} is in use. Switch to another one with {} first.", name.yellow(), "cues profile use".yellow()));
+        return Ok(());
+    }
+
+    let Some(config) = file.profiles.remove(&name) else {
+        log_failure(format!("Profile {} doesn't exist. Run {} to list profiles.", name.yellow(), "cues profile list".yellow()));
+        return Ok(());
+    };
+
+    // Only touch the credential store if this profile was ever logged in
+    if !config.expires_at.is_empty() {
+        let store = AuthStore::for_profile(&name, config.credential_store.unwrap_or_default());
+        for key in [ACCESS_TOKEN, REFRESH_TOKEN] {
+            match store.delete(key) {
+                Ok(_) | Err(CredentialError::Missing) => {},
+                Err(e) => eprintln!("{} Failed to clear stored credentials: {}", "".red(), e),
+            }
+        }
+    }
+
+    save_config_file(&file)?;
+
+    if is_machine() {
+        emit(&json!({ "removed": name }));
+        return Ok(());
+    }
+
+    println!("\n{} Removed profile {}.", "".green(), name.yellow());
+
+    Ok(())
+}
+
 // Cues CLI Handler
 pub async fn handle_cli (cmd: Cli, auth_store: AuthStore) -> Result<(), Box<dyn std::error::Error>> {
-    match cmd.command {
+    // A config file that can't be read is reported rather than written over, so no profile is lost
+    if let Err(e) = load_config_file() {
+        log_failure(format!("{}. Fix or remove it, then try again.", e));
+        return Ok(());
+    }
+
+    // Profile management works on the config file itself, not on a profile
+    if let Commands::Profile { action } = cmd.command {
+        return match action {
+            ProfileAction::Add { name } => handle_profile_add(name, cmd.api_url, cmd.credential_store),
+            ProfileAction::Use { name } => handle_profile_use(name),
+            ProfileAction::List => handle_profile_list(),
+            ProfileAction::Remove { name } => handle_profile_remove(name),
+        };
+    }
+
+    // A profile picked with --profile or CUES_PROFILE has to exist
+    let profile = active_profile();
+    if profile != DEFAULT_PROFILE && load_config().is_none() {
+        log_failure(format!("Profile {} doesn't exist. Create it with {}.", profile.yellow(), format!("cues profile add {}", profile).yellow()));
+        return Ok(());
+    }
+
+    // Resolve which backend to talk to (flag > env > config > default)
+    let api_url = resolve_base_url(cmd.api_url);
+
+    // API errors are reported to the user instead of bubbling up to main
+    if let Err(e) = handle_command(cmd.command, auth_store, &api_url).await {
+        match e.downcast_ref::<ApiError>() {
+            // The request was already printed, which is all --dry-run asks for
+            Some(ApiError::DryRun) => {},
+            Some(api_err) if is_machine() => log_err(api_err),
+            Some(api_err) => {
+                println!();
+                log_err(api_err);
+            },
+            None if is_machine() => emit_error("error", &e.to_string(), None),
+            None => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+// Dispatch a sub-command to its handler
+async fn handle_command (command: Commands, auth_store: AuthStore, api_url: &str) -> Result<(), Box<dyn error::Error>> {
+    match command {
         // PROJECT sub-commands
         Commands::Projects => {
-            handle_list_projects(&auth_store).await?;
+            let client = connect(auth_store, api_url).await?;
+            handle_list_projects(&client).await?;
         },
         Commands::Cwp => {
-            handle_cwp(&auth_store).await?;
+            handle_cwp().await?;
+        },
+        Commands::Use { project } => {
+            let client = connect(auth_store, api_url).await?;
+            handle_use(project, &client).await?;
         },
-        Commands::Use { pid } => {
-            handle_use(pid, &auth_store).await?;
+        Commands::Link { project } => {
+            let client = connect(auth_store, api_url).await?;
+            handle_link(project, &client).await?;
+        },
+        Commands::Unlink => {
+            handle_unlink().await?;
         },
         Commands::New { kind: NewProject::Project { name } } => {
-            handle_new_project(name, &auth_store).await?;
+            let client = connect(auth_store, api_url).await?;
+            handle_new_project(name, &client).await?;
+        },
+
+        Commands::Project { action } => {
+            let client = connect(auth_store, api_url).await?;
+            match action {
+                ProjectAction::Rename { project, name } => handle_project_rename(project, name, &client).await?,
+                ProjectAction::Delete { project, yes } => handle_project_delete(project, yes, &client).await?,
+            }
         },
 
         // TASK sub-commands
-        Commands::Add { title, priority, desc, due } => {
+        Commands::Add { title, priority, desc, due, editor } => {
             let priority_new: Option<PriorityType> = match priority {
                 Some(Priority::Low) => Some(PriorityType::Low),
                 Some(Priority::Medium) => Some(PriorityType::Medium),
                 Some(Priority::High) => Some(PriorityType::High),
                 None => None,
             };
-            handle_task_add(title, desc, due, priority_new, &auth_store).await?;
+            let client = connect(auth_store, api_url).await?;
+
+            // With --editor, the other flags only fill in the document
+            if editor {
+                let draft = TaskDraft { title: title.unwrap_or_default(), priority: priority_new, due, description: desc };
+                let Some(draft) = edit_draft(&draft, "new-task")? else {
+                    return Ok(());
+                };
+
+                handle_task_add(draft.title, draft.description, draft.due, draft.priority, &client).await?;
+                return Ok(());
+            }
+
+            handle_task_add(title.unwrap_or_default(), desc, due, priority_new, &client).await?;
         },
-        Commands::Tasks { all } => {
-            handle_list_tasks(all, &auth_store).await?;
+        Commands::Tasks { all, project, columns, filter, order } => {
+            let client = connect(auth_store, api_url).await?;
+            handle_list_tasks(all, project, columns, filter, order, &client).await?;
         },
-        Commands::Done { task_id } => {
-            handle_task_done(task_id, &auth_store).await?;
+        Commands::Show { task_id } => {
+            let client = connect(auth_store, api_url).await?;
+            handle_task_show(task_id, &client).await?;
         },
-        Commands::Edit { task_id, title, priority, desc, due, done } => {
+        Commands::Done { selection } => {
+            let client = connect(auth_store, api_url).await?;
+            handle_task_status(selection, StatusChange::Done, &client).await?;
+        },
+        Commands::Undone { selection } => {
+            let client = connect(auth_store, api_url).await?;
+            handle_task_status(selection, StatusChange::Undone, &client).await?;
+        },
+        Commands::Toggle { selection } => {
+            let client = connect(auth_store, api_url).await?;
+            handle_task_status(selection, StatusChange::Toggle, &client).await?;
+        },
+        Commands::Edit { task_ids, interactive: true, .. } => {
+            let id = match task_ids.as_slice() {
+                [range] => range.single(),
+                _ => None,
+            };
+            let Some(id) = id else {
+                log_failure("--interactive edits one task at a time, give a single id.".to_string());
+                return Ok(());
+            };
+
+            let client = connect(auth_store, api_url).await?;
+            handle_task_edit_interactive(id, &client).await?;
+        },
+        Commands::Edit { task_ids, filter, title, priority, desc, due, done, no_priority, no_desc, no_due, interactive: false } => {
+            // Edit's own flags share their names with the filter flags, so it only selects with --filter
+            let selection = TaskSelection { ids: task_ids, criteria: filter, scope: TaskScope::default() };
+
             let priority_new: Option<PriorityType> = match priority {
                 Some(Priority::Low) => Some(PriorityType::Low),
                 Some(Priority::Medium) => Some(PriorityType::Medium),
@@ -829,20 +1424,41 @@ pub async fn handle_cli (cmd: Cli, auth_store: AuthStore) -> Result<(), Box<dyn
                 None => None,
             };
 
-            handle_task_edit(task_id, title, desc, priority_new, due, done, &auth_store).await?;
+            // Some(None) clears the field
+            let desc = desc.map(Some).or(no_desc.then_some(None));
+            let priority_new = priority_new.map(Some).or(no_priority.then_some(None));
+            let due = due.map(Some).or(no_due.then_some(None));
+
+            let client = connect(auth_store, api_url).await?;
+            handle_task_edit(selection, title, desc, priority_new, due, done, &client).await?;
+        },
+        Commands::Delete { selection, yes } => {
+            let client = connect(auth_store, api_url).await?;
+            handle_task_delete(selection, yes, &client).await?;
+        },
+        Commands::Login { username, password_stdin } => {
+            handle_login(username, password_stdin, &auth_store, &CuesClient::new(api_url)).await?;
         },
-        Commands::Delete { task_id } => {
-            handle_task_delete(task_id, &auth_store).await?;
+        Commands::Move { selection, to } => {
+            let client = connect(auth_store, api_url).await?;
+            handle_task_move(selection, to, &client).await?;
         },
-        Commands::Login => {
-            handle_login(&auth_store).await?;
+        Commands::Undo => {
+            let client = connect(auth_store, api_url).await?;
+            handle_undo(&client).await?;
+        },
+        Commands::History { limit } => {
+            handle_history(limit)?;
         },
         Commands::Whoami => {
-            whoami(&auth_store).await?;
+            let client = connect(auth_store, api_url).await?;
+            whoami(&client).await?;
         },
         Commands::Logout => {
             handle_logout(&auth_store).await?;
-        }
+        },
+        // Handled in handle_cli
+        Commands::Profile { .. } => {},
     }
 
     Ok(())
@@ -1,8 +1,11 @@
 use clap::Parser;
-use cli::{Cli, Commands, NewProject};
+use cli::Cli;
 use commands::handle;
-use keyring::Entry;
+use utils::api::set_dry_run;
 use utils::auth::AuthStore;
+use utils::config_path::{active_profile, select_profile};
+use utils::credentials::resolve_credential_store;
+use utils::output::{failed, set_format};
 
 mod cli;
 mod commands;
@@ -13,14 +16,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse arguments from command
     let args = Cli::try_parse().unwrap_or_else(|e| e.exit());
 
-    // Initialize store for storing access and refresh tokens
-    let auth_store = AuthStore {
-        access: Entry::new("cues", "access_token")?,
-        refresh: Entry::new("cues", "refresh_token")?,
-    };
+    // Pick the output format before anything is printed
+    set_format(args.output);
+    set_dry_run(args.dry_run);
+
+    // Pick the profile before anything reads the config
+    select_profile(args.profile.clone());
+
+    // Initialize store for storing access and refresh tokens (of the selected profile)
+    let auth_store = AuthStore::for_profile(&active_profile(), resolve_credential_store(args.credential_store));
 
     // Handle CLI commands through the function
     handle::handle_cli(args, auth_store).await?;
 
+    // Let scripts tell that the command didn't go through
+    if failed() {
+        std::process::exit(1);
+    }
+
     Ok(())
 }
@@ -1,174 +1,294 @@
-use reqwest::{header::{USER_AGENT, HeaderMap, HeaderValue}, Client};
-use serde_json::Value;
-
-// Base URL for backend API
-const BASE_URL: &str = "https://cues-backend-production.up.railway.app/api";
-// const BASE_URL: &str = "http://localhost:5000/api";
-
-// SECTION - Project Management
-
-// GET: All projects
-pub async fn get_projects (token: &str) -> Result<Value, Box<dyn std::error::Error>> {
-    let res = Client::new()
-        .get(format!("{}/projects", BASE_URL))
-        .bearer_auth(token)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
-
-    Ok(res)
-}
+use std::env;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use reqwest::{header::USER_AGENT, Client, Method, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use super::config_path::load_config;
+use super::display_format::print_request;
+use super::error::ApiError;
+use super::session::Session;
+use super::types::{AuthTokens, Project, Task, User};
+
+// Default base URL for backend API (used when nothing else is configured)
+const DEFAULT_BASE_URL: &str = "https://cues-backend-production.up.railway.app/api";
+
+// How long to wait for the backend before giving up on a request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Set with --dry-run: requests that would change something are printed instead of sent
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
 
-// GET: One project
-pub async fn get_uniq_proj (token: &str, id: &u32) -> Result<Value, Box<dyn std::error::Error>> {
-    let res = Client::new()
-        .get(format!("{}/projects/{}", BASE_URL, id))
-        .bearer_auth(token)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
-
-    Ok(res)
+pub fn set_dry_run (on: bool) {
+    DRY_RUN.store(on, Ordering::Relaxed);
 }
 
-// POST: New project
-pub async fn post_project (token: &str, payload: &Value) -> Result<Value, Box<dyn std::error::Error>> {
-    let res = Client::new()
-        .post(format!("{}/projects/new", BASE_URL))
-        .bearer_auth(token)
-        .json(payload)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
-
-    Ok(res)
+pub fn is_dry_run () -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
 }
 
-// PUT: Update project
-pub async fn put_project (token: &str, id: &u32, payload: &Value) -> Result<Value, Box<dyn std::error::Error>> {
-    let res = Client::new()
-        .put(format!("{}/projects/{}", BASE_URL, id))
-        .bearer_auth(token)
-        .json(payload)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
-
-    Ok(res)
+// Resolve the backend base URL: --api-url flag, then CUES_API_URL, then config file, then default
+pub fn resolve_base_url (flag: Option<String>) -> String {
+    let url = flag
+        .or_else(|| env::var("CUES_API_URL").ok())
+        .filter(|u| !u.trim().is_empty())
+        .or_else(|| load_config().and_then(|c| c.api_url))
+        .filter(|u| !u.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+
+    url.trim().trim_end_matches('/').to_string()
 }
 
-// DELETE: Delete project
-pub async fn delete_project (token: &str, id: &u32) -> Result<Value, Box<dyn std::error::Error>> {
-    let res = Client::new()
-        .delete(format!("{}/projects/{}", BASE_URL, id))
-        .bearer_auth(token)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
-
-    Ok(res)
+// Client for the Cues backend, sharing one connection pool across all calls
+pub struct CuesClient {
+    http: Client,
+    base_url: String,
+    token: Mutex<Option<String>>,
+    session: Option<Session>,
+    // Held while refreshing, so concurrent requests that hit a 401 refresh only once
+    refreshing: tokio::sync::Mutex<()>,
 }
 
-// SECTION - Task Management
+impl CuesClient {
+    pub fn new (base_url: &str) -> Self {
+        CuesClient {
+            http: Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            base_url: base_url.to_string(),
+            token: Mutex::new(None),
+            session: None,
+            refreshing: tokio::sync::Mutex::new(()),
+        }
+    }
 
-// GET: All tasks
-pub async fn get_tasks (token: &str) -> Result<Value, Box<dyn std::error::Error>> {
-    let res = Client::new()
-        .get(format!("{}/tasks", BASE_URL))
-        .bearer_auth(token)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
+    // Set the access token sent as bearer auth with every request
+    pub fn set_token (&self, token: String) {
+        *self.token.lock().unwrap() = Some(token);
+    }
 
-    Ok(res)
-}
+    // Let the client refresh its token through the session when the backend rejects it
+    pub fn attach_session (&mut self, session: Session) {
+        self.session = Some(session);
+    }
 
-// POST: New task
-pub async fn post_task (token: &str, payload: &Value) -> Result<Value, Box<dyn std::error::Error>> {
-    let res = Client::new()
-        .post(format!("{}/tasks/new", BASE_URL))
-        .bearer_auth(token)
-        .json(payload)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
-
-    Ok(res)
-}
+    // HELPER: Build a request against the base URL
+    fn request (&self, method: Method, path: &str) -> RequestBuilder {
+        self.http
+            .request(method, format!("{}{}", self.base_url, path))
+            .header(USER_AGENT, "Cues-CLI")
+    }
+
+    // HELPER: Add the current access token to a request
+    fn authorize (&self, req: RequestBuilder) -> RequestBuilder {
+        match self.token.lock().unwrap().as_deref() {
+            Some(token) => req.bearer_auth(token),
+            None => req,
+        }
+    }
+
+    // HELPER: Send an authenticated request. On a 401, refresh the token once and retry
+    async fn send (&self, req: RequestBuilder) -> Result<Value, ApiError> {
+        // With --dry-run only reads reach the backend
+        if is_dry_run() {
+            if let Some(preview) = req.try_clone().and_then(|r| r.build().ok()) {
+                if preview.method() != Method::GET {
+                    let payload = preview.body()
+                        .and_then(|b| b.as_bytes())
+                        .and_then(|b| serde_json::from_slice::<Value>(b).ok());
+
+                    print_request(preview.method().as_str(), preview.url().as_str(), payload.as_ref());
+                    return Err(ApiError::DryRun);
+                }
+            }
+        }
+
+        let retry = req.try_clone();
+        let used = self.token.lock().unwrap().clone();
+        let res = self.dispatch(self.authorize(req)).await;
+
+        match (res, retry, &self.session) {
+            (Err(ApiError::Unauthorized(_)), Some(retry), Some(session)) => {
+                let _guard = self.refreshing.lock().await;
+
+                // Another request may have refreshed the token while this one was waiting
+                if *self.token.lock().unwrap() == used {
+                    let token = session.refresh(self).await?;
+                    self.set_token(token);
+                }
+
+                self.dispatch(self.authorize(retry)).await
+            },
+            (res, _, _) => res,
+        }
+    }
+
+    // HELPER: Send a request as-is, map HTTP error statuses to ApiError and return the JSON body
+    async fn dispatch (&self, req: RequestBuilder) -> Result<Value, ApiError> {
+        let res = req.send().await?;
+        let status = res.status();
+        let body = res.text().await?;
+
+        // Empty bodies (eg, 204 No Content) are valid, anything else must be JSON
+        let json = if body.trim().is_empty() {
+            Ok(Value::Null)
+        } else {
+            serde_json::from_str::<Value>(&body)
+        };
+
+        if status.is_success() {
+            return json.map_err(|_| ApiError::Decode(format!("expected JSON, got: {}", snippet(&body))));
+        }
+
+        let message = json.ok().as_ref().and_then(backend_message);
 
-// PUT: Update task
-pub async fn put_task (token: &str, id: &u32, payload: &Value) -> Result<Value, Box<dyn std::error::Error>> {
-    let res = Client::new()
-        .put(format!("{}/tasks/{}", BASE_URL, id))
-        .bearer_auth(token)
-        .json(payload)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
-
-    Ok(res)
+        Err(match status.as_u16() {
+            401 => ApiError::Unauthorized(message),
+            403 => ApiError::Forbidden(message),
+            404 => ApiError::NotFound(message),
+            400..=499 => ApiError::Validation(message.unwrap_or_else(|| status.to_string())),
+            code => ApiError::Server { status: code, message },
+        })
+    }
+
+    // SECTION - Project Management
+
+    // GET: All projects
+    pub async fn list_projects (&self) -> Result<Vec<Project>, ApiError> {
+        let res = self.send(self.request(Method::GET, "/projects")).await?;
+        extract(res, "projects")
+    }
+
+    // GET: One project
+    pub async fn get_project (&self, id: u32) -> Result<Project, ApiError> {
+        let res = self.send(self.request(Method::GET, &format!("/projects/{}", id))).await?;
+        extract(res, "project")
+    }
+
+    // POST: New project
+    pub async fn create_project (&self, name: &str) -> Result<Project, ApiError> {
+        let req = self.request(Method::POST, "/projects/new").json(&json!({ "name": name }));
+        extract(self.send(req).await?, "project")
+    }
+
+    // PUT: Update project
+    pub async fn update_project (&self, id: u32, payload: &Value) -> Result<Project, ApiError> {
+        let req = self.request(Method::PUT, &format!("/projects/{}", id)).json(payload);
+        extract(self.send(req).await?, "project")
+    }
+
+    // DELETE: Delete project
+    pub async fn delete_project (&self, id: u32) -> Result<Project, ApiError> {
+        let res = self.send(self.request(Method::DELETE, &format!("/projects/{}", id))).await?;
+        extract(res, "project")
+    }
+
+    // SECTION - Task Management
+
+    // GET: All tasks
+    pub async fn list_tasks (&self) -> Result<Vec<Task>, ApiError> {
+        let res = self.send(self.request(Method::GET, "/tasks")).await?;
+        extract(res, "tasks")
+    }
+
+    // GET: One task (the backend has no endpoint for a single task, so it's found in the task list)
+    pub async fn get_task (&self, id: u32) -> Result<Task, ApiError> {
+        self.list_tasks().await?
+            .into_iter()
+            .find(|t| t.id == id)
+            .ok_or_else(|| ApiError::NotFound(Some(format!("Task {} not found", id))))
+    }
+
+    // POST: New task
+    pub async fn create_task (&self, payload: &Value) -> Result<Task, ApiError> {
+        let req = self.request(Method::POST, "/tasks/new").json(payload);
+        extract(self.send(req).await?, "task")
+    }
+
+    // PUT: Update task
+    pub async fn update_task (&self, id: u32, payload: &Value) -> Result<Task, ApiError> {
+        let req = self.request(Method::PUT, &format!("/tasks/{}", id)).json(payload);
+        extract(self.send(req).await?, "task")
+    }
+
+    // DELETE: Delete task
+    pub async fn delete_task (&self, id: u32) -> Result<Task, ApiError> {
+        let res = self.send(self.request(Method::DELETE, &format!("/tasks/{}", id))).await?;
+        extract(res, "task")
+    }
+
+    // SECTION - Authentication
+
+    // GET: User details
+    pub async fn get_user (&self) -> Result<User, ApiError> {
+        let res = self.send(self.request(Method::GET, "/auth/user")).await?;
+        extract(res, "user")
+    }
+
+    // POST: Log in (with either a username or an email address)
+    pub async fn login (&self, username_or_email: &str, password: &str) -> Result<AuthTokens, ApiError> {
+        let mut payload = json!({
+            "password": password,
+        });
+
+        if username_or_email.contains('@') {
+            payload["email"] = json!(username_or_email);
+        } else {
+            payload["username"] = json!(username_or_email);
+        }
+
+        let req = self.request(Method::POST, "/auth/login").json(&payload);
+        tokens(self.dispatch(req).await?)
+    }
+
+    // POST: Refresh access token
+    pub async fn refresh (&self, refresh_token: &str) -> Result<AuthTokens, ApiError> {
+        let req = self.request(Method::POST, "/auth/refresh").json(&json!({ "refresh_token": refresh_token }));
+        tokens(self.dispatch(req).await?)
+    }
 }
 
-// DELETE: Delete task
-pub async fn delete_task (token: &str, id: &u32) -> Result<Value, Box<dyn std::error::Error>> {
-    let res = Client::new()
-        .delete(format!("{}/tasks/{}", BASE_URL, id))
-        .bearer_auth(token)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
-
-    Ok(res)
+// HELPER: Pull `key` out of a response envelope like `{ "task": {...} }`
+fn extract<T: DeserializeOwned> (mut res: Value, key: &str) -> Result<T, ApiError> {
+    match res.get_mut(key) {
+        Some(val) => Ok(serde_json::from_value(val.take())?),
+        None => Err(missing(&res, key)),
+    }
 }
 
-// SECTION - Authentication
+// HELPER: Decode the token pair returned by the auth endpoints
+fn tokens (res: Value) -> Result<AuthTokens, ApiError> {
+    if res.get("accessToken").is_none() {
+        return Err(missing(&res, "accessToken"));
+    }
 
-// GET: User details
-pub async fn get_user (token: &str) -> Result<Value, Box<dyn std::error::Error>> {
-    let res = Client::new()
-        .get(format!("{}/auth/user", BASE_URL))
-        .bearer_auth(token)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
+    Ok(serde_json::from_value(res)?)
+}
 
-    Ok(res)
+// HELPER: Error for a successful response that lacks the expected field
+fn missing (res: &Value, key: &str) -> ApiError {
+    match backend_message(res) {
+        Some(msg) => ApiError::Validation(msg),
+        None => ApiError::Decode(format!("missing `{}` in response", key)),
+    }
 }
 
-// POST: Log in
-pub async fn post_login (payload: &Value) -> Result<Value, Box<dyn std::error::Error>> {
-    let res = Client::new()
-        .post(format!("{}/auth/login", BASE_URL))
-        .header(USER_AGENT, "Cues-CLI")
-        .json(payload)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
-
-    Ok(res)
+// HELPER: The backend's `message` or `error` field, if any
+fn backend_message (res: &Value) -> Option<String> {
+    res.get("message")
+        .or_else(|| res.get("error"))
+        .and_then(|m| m.as_str())
+        .map(|m| m.to_string())
 }
 
-// POST: Refresh access token
-pub async fn post_refresh (payload: &Value) -> Result<Value, Box<dyn std::error::Error>> {
-    let res = Client::new()
-        .post(format!("{}/auth/refresh", BASE_URL))
-        .header(USER_AGENT, "Cues-CLI")
-        .json(payload)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
-
-    Ok(res)
+// HELPER: First line of a non-JSON body (eg, an HTML error page), shortened for display
+fn snippet (body: &str) -> String {
+    let line = body.trim().lines().next().unwrap_or("");
+    match line.char_indices().nth(80) {
+        Some((i, _)) => format!("{}...", &line[..i]),
+        None => line.to_string(),
+    }
 }
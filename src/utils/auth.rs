@@ -1,44 +1,181 @@
-use colored::Colorize;
-use keyring::Entry;
-use chrono::{DateTime, Local};
-use serde_json::json;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Local, Utc};
+use serde_json::Value;
 
-use crate::utils::display_format::log_err;
+use super::config_path::DEFAULT_PROFILE;
+use super::credentials::{CredentialError, CredentialStore};
+use super::types::AuthTokens;
 
-use super::api::post_refresh;
+// Tokens are treated as expired this long before their actual expiry, to absorb clock differences
+const CLOCK_SKEW_SECS: i64 = 30;
 
-// Storing access and refresh tokens in a keyring
+// Names under which the tokens are stored
+pub const ACCESS_TOKEN: &str = "access_token";
+pub const REFRESH_TOKEN: &str = "refresh_token";
+
+// Storing access and refresh tokens in the selected credential store.
+// Nothing is opened until a token is read or written, so commands that don't need it work without one.
 #[derive(Debug)]
 pub struct AuthStore {
-    pub access: Entry,
-    pub refresh: Entry,
+    service: String,
+    kind: CredentialStore,
+}
+
+impl AuthStore {
+    pub fn new (service: &str, kind: CredentialStore) -> Self {
+        AuthStore { service: service.to_string(), kind }
+    }
+
+    // Credentials of a profile. The default profile keeps the plain "cues" service, so existing logins carry over
+    pub fn for_profile (profile: &str, kind: CredentialStore) -> Self {
+        if profile == DEFAULT_PROFILE {
+            AuthStore::new("cues", kind)
+        } else {
+            AuthStore::new(&format!("cues:{}", profile), kind)
+        }
+    }
+
+    // Same credentials, kept in a different store
+    pub fn with_kind (&self, kind: CredentialStore) -> Self {
+        AuthStore::new(&self.service, kind)
+    }
+
+    pub fn kind (&self) -> CredentialStore {
+        self.kind
+    }
+
+    pub fn get (&self, key: &str) -> Result<String, CredentialError> {
+        self.kind.get(&self.service, key)
+    }
+
+    pub fn set (&self, key: &str, value: &str) -> Result<(), CredentialError> {
+        self.kind.set(&self.service, key, value)
+    }
+
+    pub fn delete (&self, key: &str) -> Result<(), CredentialError> {
+        self.kind.delete(&self.service, key)
+    }
 }
 
 // Check if existing JWT is expired
 pub fn jwt_expired (expiry: &str) -> bool {
     match DateTime::parse_from_rfc3339(expiry) {
-        Ok(ex) => ex < Local::now(),
+        Ok(ex) => ex - Duration::seconds(CLOCK_SKEW_SECS) < Local::now(),
         Err(_) => true,
     }
 }
 
-// Provide a refresh token and this function creates and returns a new access token and returns
-pub async fn refresh_access_token (r: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let payload = json!({
-        "refresh_token": r,
-    });
-
-    // API call
-    let res = post_refresh(&payload).await?;
-
-    // Extracting data from response
-    if let Some(t) = res.get("accessToken") {
-        // println!("New token in helper: {}", t);
-        let token = t.as_str().unwrap_or("").trim_matches('"');
-        return Ok(token.to_string());
-    } else {
-        println!();
-        log_err(res);
-        return Err(Box::from(""));
+// Read the `exp` claim from a JWT (the signature is not verified, that is the server's job)
+pub fn jwt_expiry (token: &str) -> Option<DateTime<Utc>> {
+    let payload = token.split('.').nth(1)?;
+    let bytes = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    let claims: Value = serde_json::from_slice(&bytes).ok()?;
+
+    DateTime::from_timestamp(claims.get("exp")?.as_i64()?, 0)
+}
+
+// Parse an `expiresIn` value: seconds as a number or string, or a duration like "15m", "1h", "7d".
+// Values that aren't positive, or too large to add to a date, are ignored like unknown units
+fn parse_expires_in (val: &Value) -> Option<Duration> {
+    if let Some(secs) = val.as_i64() {
+        return Duration::try_seconds(secs).filter(|d| *d > Duration::zero());
+    }
+
+    let s = val.as_str()?.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let num: i64 = num.parse().ok()?;
+
+    let duration = match unit.trim() {
+        "" | "s" => Duration::try_seconds(num),
+        "m" => Duration::try_minutes(num),
+        "h" => Duration::try_hours(num),
+        "d" => Duration::try_days(num),
+        _ => None,
+    };
+
+    duration.filter(|d| *d > Duration::zero())
+}
+
+// Work out when a freshly issued access token expires, as an RFC 3339 timestamp.
+// Uses the earlier of the JWT `exp` claim and the server's `expiresIn`, falling back to one hour.
+pub fn token_expiry (tokens: &AuthTokens) -> String {
+    let from_claim = jwt_expiry(&tokens.access_token);
+    let from_server = tokens.expires_in.as_ref()
+        .and_then(parse_expires_in)
+        .and_then(|d| Utc::now().checked_add_signed(d));
+
+    let expiry = match (from_claim, from_server) {
+        (Some(a), Some(b)) => a.min(b),
+        (Some(a), None) | (None, Some(a)) => a,
+        (None, None) => Utc::now() + Duration::hours(1),
+    };
+
+    expiry.with_timezone(&Local).to_rfc3339()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Unsigned JWT with the given claims
+    fn token (claims: Value) -> String {
+        format!("e30.{}.sig", URL_SAFE_NO_PAD.encode(claims.to_string()))
+    }
+
+    #[test]
+    fn expiry_claim () {
+        assert_eq!(jwt_expiry(&token(json!({ "exp": 1_760_000_000 }))), DateTime::from_timestamp(1_760_000_000, 0));
+        assert_eq!(jwt_expiry(&token(json!({ "sub": "dev" }))), None);
+        assert_eq!(jwt_expiry(&token(json!({ "exp": "soon" }))), None);
+        assert_eq!(jwt_expiry(&token(json!({ "exp": i64::MAX }))), None);
+        assert_eq!(jwt_expiry("not a token"), None);
+        assert_eq!(jwt_expiry("e30.!!!.sig"), None);
+    }
+
+    #[test]
+    fn expires_in () {
+        let cases = [
+            (json!(900), Some(Duration::seconds(900))),
+            (json!("900"), Some(Duration::seconds(900))),
+            (json!("30s"), Some(Duration::seconds(30))),
+            (json!("15m"), Some(Duration::minutes(15))),
+            (json!("1h"), Some(Duration::hours(1))),
+            (json!(" 7d "), Some(Duration::days(7))),
+            (json!(-5), None),
+            (json!(0), None),
+            (json!("0"), None),
+            (json!("0m"), None),
+            (json!("-5"), None),
+            (json!("2w"), None),
+            (json!("1.5h"), None),
+            (json!("h"), None),
+            (json!("soon"), None),
+            (json!(null), None),
+            (json!(i64::MAX), None),
+            (json!(u64::MAX), None),
+            (json!("9999999999999999d"), None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse_expires_in(&input), expected, "parsing {}", input);
+        }
+    }
+
+    #[test]
+    fn unusable_expires_in_falls_back_to_the_claim () {
+        let exp = Utc::now().timestamp() + 600;
+
+        for expires_in in [json!(i64::MAX), json!(-5), json!(0)] {
+            let tokens = AuthTokens {
+                access_token: token(json!({ "exp": exp })),
+                refresh_token: None,
+                expires_in: Some(expires_in.clone()),
+            };
+
+            let expiry = DateTime::parse_from_rfc3339(&token_expiry(&tokens)).unwrap();
+            assert_eq!(expiry.timestamp(), exp, "expiresIn {}", expires_in);
+        }
     }
 }
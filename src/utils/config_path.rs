@@ -1,26 +1,208 @@
+use std::collections::BTreeMap;
 use std::fs;
+use std::io;
 use std::path::PathBuf;
-use std::io::{self, Write};
+use std::sync::OnceLock;
 use directories::ProjectDirs;
 use serde::{Serialize, Deserialize};
 
-// Define config structure
-#[derive(Serialize, Deserialize, Debug)]
+use super::credentials::CredentialStore;
+use super::dates::{Clock, DateStyle};
+use super::session::env_var;
+
+// Profile used when none has been created or selected
+pub const DEFAULT_PROFILE: &str = "default";
+
+// Profile picked for this run with --profile or CUES_PROFILE (overrides the active profile)
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+// Define config structure (one per profile)
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct Config {
+    // Missing in hand-written profiles, which may only set a few options
+    #[serde(default)]
     pub current_project: String,
+    #[serde(default)]
     pub current_project_id: u32,
+    #[serde(default)]
     pub expires_at: String,
+
+    // Backend base URL override (self-hosted or staging instances)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+
+    // Where tokens are kept, when not in the OS keyring
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_store: Option<CredentialStore>,
+
+    // Hour (0-23) for due dates given without a time, like "friday"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_hour: Option<u32>,
+
+    // How dates are shown: absolute (default), iso or relative
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_style: Option<DateStyle>,
+
+    // strftime pattern for absolute dates, eg "%a %d %b %H:%M"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_format: Option<String>,
+
+    // 12h or 24h (default) clock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<Clock>,
+
+    // Timezone dates are shown in, eg "Europe/Berlin" (instead of the system's)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+
+    // Language of month and day names, eg "de_DE" (instead of English)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+// Layout of the config file: every profile, and the one in use
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConfigFile {
+    pub active_profile: String,
+    pub profiles: BTreeMap<String, Config>,
+}
+
+impl Default for ConfigFile {
+    fn default () -> Self {
+        ConfigFile {
+            active_profile: DEFAULT_PROFILE.to_string(),
+            profiles: BTreeMap::new(),
+        }
+    }
 }
 
 // Returns config directory
+pub fn get_config_dir () -> Option<PathBuf> {
+    ProjectDirs::from("dev", "aetheros", "cues").map(|proj_dirs| proj_dirs.config_dir().to_path_buf())
+}
+
+// Returns path of the config file
 pub fn get_config_path () -> Option<PathBuf> {
-    ProjectDirs::from("dev", "aetheros", "cues").map(|proj_dirs| proj_dirs.config_dir().join("config.json"))
+    get_config_dir().map(|dir| dir.join("config.json"))
+}
+
+// Use `flag` (or CUES_PROFILE) as the profile for this run, instead of the active one
+pub fn select_profile (flag: Option<String>) {
+    if let Some(name) = flag.filter(|p| !p.trim().is_empty()).or_else(|| env_var("CUES_PROFILE")) {
+        let _ = PROFILE.set(name.trim().to_string());
+    }
 }
 
-// Loads and returns data from config file
+// Name of the profile this run works with
+pub fn active_profile () -> String {
+    match PROFILE.get() {
+        Some(name) => name.clone(),
+        None => load_config_file().ok().flatten().map(|f| f.active_profile).unwrap_or_else(|| DEFAULT_PROFILE.to_string()),
+    }
+}
+
+// Loads the whole config file (None when there is none yet). A file that can't be parsed is an error,
+// so that it's reported instead of being replaced by a fresh one
+pub fn load_config_file () -> io::Result<Option<ConfigFile>> {
+    let Some(path) = get_config_path() else {
+        return Ok(None);
+    };
+
+    let data = match fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    parse_config_file(&data)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("The config file {} is invalid: {}", path.display(), e)))
+}
+
+// HELPER: Parse the config file. Files from before profiles existed become the default profile
+fn parse_config_file (data: &str) -> serde_json::Result<ConfigFile> {
+    let value: serde_json::Value = serde_json::from_str(data)?;
+
+    if !value.is_object() {
+        return Err(serde::de::Error::custom("expected a JSON object"));
+    }
+
+    if value.get("profiles").is_some() {
+        return serde_json::from_str(data);
+    }
+
+    let legacy: Config = serde_json::from_str(data)?;
+    let mut file = ConfigFile::default();
+    file.profiles.insert(DEFAULT_PROFILE.to_string(), legacy);
+
+    Ok(file)
+}
+
+// Writes the whole config file, creating the config directory if needed
+pub fn save_config_file (file: &ConfigFile) -> io::Result<()> {
+    let path = get_config_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Could not determine config directory"))?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let json = serde_json::to_string_pretty(file)?;
+    fs::write(path, json)
+}
+
+// Loads and returns the active profile's config
 pub fn load_config () -> Option<Config> {
-    let path = get_config_path()?;
-    let data = fs::read_to_string(path).ok()?;
+    load_config_file().ok()??.profiles.remove(&active_profile())
+}
+
+// Writes the active profile's config, leaving the other profiles untouched
+pub fn save_config (config: &Config) -> io::Result<()> {
+    let mut file = load_config_file()?.unwrap_or_default();
+    file.profiles.insert(active_profile(), config.clone());
+    save_config_file(&file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_config_becomes_default_profile () {
+        let file = parse_config_file(r#"{ "current_project": "Cues CLI", "current_project_id": 1, "expires_at": "2026-10-18T10:00:00+00:00" }"#).unwrap();
+
+        assert_eq!(file.active_profile, DEFAULT_PROFILE);
+        assert_eq!(file.profiles.len(), 1);
+
+        let config = &file.profiles[DEFAULT_PROFILE];
+        assert_eq!(config.current_project, "Cues CLI");
+        assert_eq!(config.current_project_id, 1);
+        assert_eq!(config.expires_at, "2026-10-18T10:00:00+00:00");
+    }
+
+    #[test]
+    fn profiles_with_only_some_options () {
+        let file = parse_config_file(r#"{ "active_profile": "work", "profiles": { "work": { "due_hour": 8, "date_style": "relative" }, "home": {} } }"#).unwrap();
+
+        assert_eq!(file.active_profile, "work");
+        assert_eq!(file.profiles["work"].due_hour, Some(8));
+        assert_eq!(file.profiles["work"].date_style, Some(DateStyle::Relative));
+        assert!(file.profiles["home"].expires_at.is_empty());
+    }
+
+    #[test]
+    fn malformed_files_are_errors () {
+        let cases = [
+            "",
+            "{ \"active_profile\": ",
+            "[]",
+            r#"{ "active_profile": "default", "profiles": { "default": { "date_style": "Relative" } } }"#,
+            r#"{ "active_profile": "default", "profiles": { "default": { "due_hour": "eight" } } }"#,
+            r#"{ "active_profile": "default", "profiles": [] }"#,
+            r#"{ "current_project_id": "one" }"#,
+        ];
 
-    serde_json::from_str(&data).ok()
+        for data in cases {
+            assert!(parse_config_file(data).is_err(), "parsing {:?}", data);
+        }
+    }
 }
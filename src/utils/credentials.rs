@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use keyring::Entry;
+use serde::{Deserialize, Serialize};
+
+use super::config_path::{get_config_dir, load_config};
+use super::session::env_var;
+
+// Passphrase for the encrypted file, asked for at most once per run
+static PASSPHRASE: OnceLock<String> = OnceLock::new();
+
+// Where access and refresh tokens are kept
+#[derive(clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum CredentialStore {
+    // OS keyring (Keychain, Credential Manager, Secret Service)
+    #[default]
+    Keyring,
+    // File in the config directory, encrypted with a passphrase
+    EncryptedFile,
+    // Plain-text file readable only by the current user (insecure, opt-in)
+    File,
+}
+
+#[derive(Debug)]
+pub enum CredentialError {
+    // Nothing stored under this name
+    Missing,
+    // OS keyring failed or is unavailable
+    Keyring(keyring::Error),
+    // Credentials file couldn't be read or written
+    Io(io::Error),
+    // Wrong passphrase, or the encrypted file is corrupted
+    Decrypt,
+}
+
+impl fmt::Display for CredentialError {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredentialError::Missing => write!(f, "No stored credentials found"),
+            CredentialError::Keyring(e) => write!(f, "System keyring error: {}", e),
+            CredentialError::Io(e) => write!(f, "Couldn't access the credentials file: {}", e),
+            CredentialError::Decrypt => write!(f, "Couldn't decrypt the credentials file (wrong passphrase?)"),
+        }
+    }
+}
+
+impl std::error::Error for CredentialError {}
+
+impl From<keyring::Error> for CredentialError {
+    fn from (e: keyring::Error) -> Self {
+        match e {
+            keyring::Error::NoEntry => CredentialError::Missing,
+            e => CredentialError::Keyring(e),
+        }
+    }
+}
+
+impl From<io::Error> for CredentialError {
+    fn from (e: io::Error) -> Self {
+        CredentialError::Io(e)
+    }
+}
+
+// On-disk layout of the encrypted credentials file (all fields base64)
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    salt: String,
+    nonce: String,
+    data: String,
+}
+
+// Pick the credential store: --credential-store flag, then config file, then the OS keyring
+pub fn resolve_credential_store (flag: Option<CredentialStore>) -> CredentialStore {
+    flag.or_else(|| load_config().and_then(|c| c.credential_store))
+        .unwrap_or_default()
+}
+
+// Check whether the OS keyring can actually be used on this machine
+pub fn keyring_available () -> bool {
+    match Entry::new("cues", "access_token").and_then(|e| e.get_password()) {
+        Ok(_) | Err(keyring::Error::NoEntry) => true,
+        Err(_) => false,
+    }
+}
+
+impl CredentialStore {
+    // Read the secret stored as `key` under `service`
+    pub fn get (&self, service: &str, key: &str) -> Result<String, CredentialError> {
+        match self {
+            CredentialStore::Keyring => Ok(Entry::new(service, key)?.get_password()?),
+            _ => self.read_file()?
+                .remove(&file_key(service, key))
+                .ok_or(CredentialError::Missing),
+        }
+    }
+
+    // Store `value` as `key` under `service`
+    pub fn set (&self, service: &str, key: &str, value: &str) -> Result<(), CredentialError> {
+        match self {
+            CredentialStore::Keyring => Ok(Entry::new(service, key)?.set_password(value)?),
+            _ => {
+                let mut creds = self.read_file()?;
+                creds.insert(file_key(service, key), value.to_string());
+                self.write_file(&creds)
+            }
+        }
+    }
+
+    // Remove `key` under `service`
+    pub fn delete (&self, service: &str, key: &str) -> Result<(), CredentialError> {
+        match self {
+            CredentialStore::Keyring => Ok(Entry::new(service, key)?.delete_credential()?),
+            _ => {
+                let mut creds = self.read_file()?;
+                if creds.remove(&file_key(service, key)).is_none() {
+                    return Err(CredentialError::Missing);
+                }
+                self.write_file(&creds)
+            }
+        }
+    }
+
+    // HELPER: Path of the credentials file for file-based stores
+    fn path (&self) -> Result<PathBuf, CredentialError> {
+        let dir = get_config_dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Could not determine config directory"))?;
+
+        Ok(match self {
+            CredentialStore::EncryptedFile => dir.join("credentials.enc"),
+            _ => dir.join("credentials.json"),
+        })
+    }
+
+    // HELPER: Load every credential from the file (empty if it doesn't exist yet)
+    fn read_file (&self) -> Result<BTreeMap<String, String>, CredentialError> {
+        let data = match fs::read(self.path()?) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let plain = match self {
+            CredentialStore::EncryptedFile => unseal(&data)?,
+            _ => data,
+        };
+
+        serde_json::from_slice(&plain).map_err(|e| CredentialError::Io(e.into()))
+    }
+
+    // HELPER: Write every credential to the file, readable only by the current user
+    fn write_file (&self, creds: &BTreeMap<String, String>) -> Result<(), CredentialError> {
+        let path = self.path()?;
+        let plain = serde_json::to_vec_pretty(creds).map_err(|e| CredentialError::Io(e.into()))?;
+
+        let data = match self {
+            CredentialStore::EncryptedFile => seal(&plain, !path.exists())?,
+            _ => plain,
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+
+            // Tighten permissions on files created before (or by something else)
+            if path.exists() {
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+            }
+        }
+
+        options.open(&path)?.write_all(&data)?;
+
+        Ok(())
+    }
+}
+
+// HELPER: Key under which a credential is kept in the file stores
+fn file_key (service: &str, key: &str) -> String {
+    format!("{}/{}", service, key)
+}
+
+// HELPER: Passphrase from CUES_PASSPHRASE, or asked for on the terminal (twice when creating the file)
+fn passphrase (creating: bool) -> Result<String, CredentialError> {
+    if let Some(p) = PASSPHRASE.get() {
+        return Ok(p.clone());
+    }
+
+    let pass = match env_var("CUES_PASSPHRASE") {
+        Some(p) => p,
+        None if creating => {
+            let pass = rpassword::prompt_password("Choose a passphrase for the credentials file: ")?;
+            let again = rpassword::prompt_password("Repeat the passphrase: ")?;
+
+            if pass != again {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "Passphrases don't match").into());
+            }
+            pass
+        },
+        None => rpassword::prompt_password("Passphrase for the credentials file: ")?,
+    };
+
+    Ok(PASSPHRASE.get_or_init(|| pass).clone())
+}
+
+// HELPER: Derive a 256-bit key from the passphrase (Argon2id)
+fn derive_key (pass: &str, salt: &[u8]) -> Result<Key, CredentialError> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(pass.as_bytes(), salt, &mut key)
+        .map_err(|_| CredentialError::Decrypt)?;
+
+    Ok(key)
+}
+
+// HELPER: Encrypt file contents with a fresh salt and nonce
+fn seal (plain: &[u8], creating: bool) -> Result<Vec<u8>, CredentialError> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(&passphrase(creating)?, &salt)?);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let data = cipher.encrypt(&nonce, plain).map_err(|_| CredentialError::Decrypt)?;
+
+    let sealed = Sealed {
+        salt: STANDARD.encode(salt),
+        nonce: STANDARD.encode(nonce),
+        data: STANDARD.encode(data),
+    };
+
+    serde_json::to_vec_pretty(&sealed).map_err(|e| CredentialError::Io(e.into()))
+}
+
+// HELPER: Decrypt file contents written by `seal`
+fn unseal (data: &[u8]) -> Result<Vec<u8>, CredentialError> {
+    let sealed: Sealed = serde_json::from_slice(data).map_err(|_| CredentialError::Decrypt)?;
+    let decode = |s: &str| STANDARD.decode(s).map_err(|_| CredentialError::Decrypt);
+
+    let salt = decode(&sealed.salt)?;
+    let nonce = decode(&sealed.nonce)?;
+    if nonce.len() != 12 {
+        return Err(CredentialError::Decrypt);
+    }
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(&passphrase(false)?, &salt)?);
+    cipher.decrypt(Nonce::from_slice(&nonce), decode(&sealed.data)?.as_slice())
+        .map_err(|_| CredentialError::Decrypt)
+}
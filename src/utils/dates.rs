@@ -0,0 +1,614 @@
+use std::fmt::Write;
+use std::sync::OnceLock;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, Locale, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use colored::{ColoredString, Colorize};
+use serde::{Serialize, Deserialize};
+
+use super::config_path::load_config;
+
+// SECTION - Parsing
+
+// Hour given to due dates without a time ("friday"), unless `due_hour` is set in the config
+pub const DEFAULT_DUE_HOUR: u32 = 9;
+
+// Parse a due date phrase ("friday 5pm", "in 3 days", "aug 20" ...) into an RFC 3339 UTC timestamp
+pub fn natural_to_datetime (input: &str) -> Option<String> {
+    let hour = load_config()
+        .and_then(|c| c.due_hour)
+        .filter(|h| *h < 24)
+        .unwrap_or(DEFAULT_DUE_HOUR);
+
+    // Offsets like "in 2h" count from the current minute
+    let now = Local::now().naive_local().with_second(0)?.with_nanosecond(0)?;
+
+    let due = parse_natural(input, now, hour)?;
+    let local = due.and_local_timezone(Local).earliest()?;
+
+    Some(local.with_timezone(&Utc).to_rfc3339())
+}
+
+// Parse a due date phrase against the wall clock time `now`. Understood phrases:
+//
+// - Days: today, tomorrow, weekdays ("fri", "friday" is the next one, never today), "next friday" (in next week),
+//   "next week" (its monday), "next month" (its first day), "in 3 days", "in 2 weeks", "in 1 month"
+// - Dates: 2025-08-20, 20/08/2025, 20-08-2025, 20/08, "aug 20", "20th august 2026" (no year means the next one)
+// - Times, after a day or alone (today, or tomorrow if already past): 17:30, 5pm, "5:30 pm", noon
+// - Exact points: "in 2h", "in 90 minutes", eod / "end of day", eow / "end of week", eom / "end of month"
+//
+// Days without a time are due at `default_hour`. Filler words ("at", "on", "by") are ignored
+pub fn parse_natural (input: &str, now: NaiveDateTime, default_hour: u32) -> Option<NaiveDateTime> {
+    let input = input.trim().to_lowercase();
+    let tokens: Vec<&str> = input.split_whitespace()
+        .filter(|t| !matches!(*t, "at" | "on" | "by"))
+        .collect();
+
+    let today = now.date();
+    let end_of = |date: NaiveDate| date.and_hms_opt(23, 59, 0);
+
+    // Exact points in time, which take no time of day
+    match tokens.join(" ").as_str() {
+        "eod" | "end of day" | "end of today" => return end_of(today),
+        "eow" | "end of week" | "end of the week" => return end_of(today + Duration::days(6 - days_from_monday(today))),
+        "eom" | "end of month" | "end of the month" => return end_of(last_of_month(today)?),
+        _ => {},
+    }
+
+    if let Some(dt) = parse_iso_datetime(&input) {
+        return Some(dt);
+    }
+
+    if let ["in", rest @ ..] = tokens.as_slice() {
+        match parse_amount(rest) {
+            Some((amount, Unit::Minutes, used)) if used == rest.len() => return now.checked_add_signed(Duration::minutes(amount)),
+            Some((amount, Unit::Hours, used)) if used == rest.len() => return now.checked_add_signed(Duration::hours(amount)),
+            _ => {},
+        }
+    }
+
+    let (date, rest) = match parse_date(&tokens, today) {
+        Some((date, used)) => (Some(date), &tokens[used..]),
+        None => (None, tokens.as_slice()),
+    };
+
+    let time = match rest {
+        [] => None,
+        _ => Some(parse_time(&rest.concat())?),
+    };
+
+    match (date, time) {
+        (Some(date), Some(time)) => Some(date.and_time(time)),
+        (Some(date), None) => date.and_hms_opt(default_hour, 0, 0),
+        // A time alone is the next time the clock shows it
+        (None, Some(time)) => {
+            let at = today.and_time(time);
+            Some(if at > now { at } else { at + Duration::days(1) })
+        },
+        (None, None) => None,
+    }
+}
+
+// Length of an offset like "3 days" or "2h"
+enum Unit {
+    Minutes,
+    Hours,
+    Days,
+    Weeks,
+    Months,
+}
+
+// HELPER: Find the day at the start of `tokens`, with how many tokens it took
+fn parse_date (tokens: &[&str], today: NaiveDate) -> Option<(NaiveDate, usize)> {
+    let first = *tokens.first()?;
+
+    match first {
+        "today" => return Some((today, 1)),
+        "tomorrow" | "tmrw" => return Some((today + Duration::days(1), 1)),
+        _ => {},
+    }
+
+    if let Some(weekday) = parse_weekday(first) {
+        let ahead = (weekday.num_days_from_monday() as i64 - days_from_monday(today)).rem_euclid(7);
+        return Some((today + Duration::days(if ahead == 0 { 7 } else { ahead }), 1));
+    }
+
+    if first == "next" {
+        let next_monday = today + Duration::days(7 - days_from_monday(today));
+        let second = *tokens.get(1)?;
+
+        return match second {
+            "week" => Some((next_monday, 2)),
+            "month" => Some((first_of_month(today)?.checked_add_months(Months::new(1))?, 2)),
+            _ => Some((next_monday + Duration::days(parse_weekday(second)?.num_days_from_monday() as i64), 2)),
+        };
+    }
+
+    if first == "in" {
+        let (amount, unit, used) = parse_amount(&tokens[1..])?;
+
+        let date = match unit {
+            Unit::Days => today.checked_add_signed(Duration::days(amount))?,
+            Unit::Weeks => today.checked_add_signed(Duration::weeks(amount))?,
+            Unit::Months => today.checked_add_months(Months::new(u32::try_from(amount).ok()?))?,
+            Unit::Minutes | Unit::Hours => return None,
+        };
+
+        return Some((date, used + 1));
+    }
+
+    // Numeric dates
+    for format in ["%Y-%m-%d", "%d/%m/%Y", "%d-%m-%Y", "%d.%m.%Y"] {
+        if let Ok(date) = NaiveDate::parse_from_str(first, format) {
+            return Some((date, 1));
+        }
+    }
+
+    // Day and month without a year ("20/08")
+    if let Some((day, month)) = first.split_once('/') {
+        let date = upcoming(today, month.parse().ok()?, day.parse().ok()?)?;
+        return Some((date, 1));
+    }
+
+    // Month names, either way around, with an optional year ("aug 20", "20th august 2026")
+    let second = *tokens.get(1)?;
+    let (month, day) = match (parse_month(first), parse_month(second)) {
+        (Some(month), None) => (month, parse_day(second)?),
+        (None, Some(month)) => (month, parse_day(first)?),
+        _ => return None,
+    };
+
+    match tokens.get(2).filter(|t| t.len() == 4).and_then(|t| t.parse::<i32>().ok()) {
+        Some(year) => Some((NaiveDate::from_ymd_opt(year, month, day)?, 3)),
+        None => Some((upcoming(today, month, day)?, 2)),
+    }
+}
+
+// HELPER: Parse a time of day: 17:30, 5pm, 5:30pm, noon
+fn parse_time (input: &str) -> Option<NaiveTime> {
+    if input == "noon" {
+        return NaiveTime::from_hms_opt(12, 0, 0);
+    }
+
+    let (clock, pm) = match input.strip_suffix("pm") {
+        Some(clock) => (clock, Some(true)),
+        None => match input.strip_suffix("am") {
+            Some(clock) => (clock, Some(false)),
+            None => (input, None),
+        },
+    };
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((h, m)) if m.len() == 2 => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        Some(_) => return None,
+        // A bare number is only a time with am/pm ("5pm", not "5")
+        None if pm.is_some() => (clock.parse::<u32>().ok()?, 0),
+        None => return None,
+    };
+
+    let hour = match pm {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(true) => hour % 12 + 12,
+        Some(false) => hour % 12,
+        None => hour,
+    };
+
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+// HELPER: ISO date and time in one token ("2025-08-20t17:30", already lowercased)
+fn parse_iso_datetime (input: &str) -> Option<NaiveDateTime> {
+    ["%Y-%m-%dt%H:%M", "%Y-%m-%dt%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+}
+
+// HELPER: Parse an amount of time at the start of `tokens` ("3 days", "2h", "90 minutes" ...), with how many tokens it took
+fn parse_amount (tokens: &[&str]) -> Option<(i64, Unit, usize)> {
+    let (amount, unit, used) = match tokens {
+        [amount, unit, ..] if amount.chars().all(|c| c.is_ascii_digit()) => (*amount, *unit, 2),
+        [compact, ..] => {
+            let split = compact.find(|c: char| !c.is_ascii_digit())?;
+            (&compact[..split], &compact[split..], 1)
+        },
+        [] => return None,
+    };
+
+    let amount = amount.parse::<u32>().ok()? as i64;
+
+    let unit = match unit {
+        "m" | "min" | "mins" | "minute" | "minutes" => Unit::Minutes,
+        "h" | "hr" | "hrs" | "hour" | "hours" => Unit::Hours,
+        "d" | "day" | "days" => Unit::Days,
+        "w" | "wk" | "wks" | "week" | "weeks" => Unit::Weeks,
+        "mo" | "month" | "months" => Unit::Months,
+        _ => return None,
+    };
+
+    Some((amount, unit, used))
+}
+
+// HELPER: Weekday from its name or abbreviation
+fn parse_weekday (word: &str) -> Option<Weekday> {
+    match word {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tues" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thur" | "thurs" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+// HELPER: Month number from its name or abbreviation
+fn parse_month (word: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = ["january", "february", "march", "april", "may", "june", "july", "august", "september", "october", "november", "december"];
+
+    if word.len() < 3 {
+        return None;
+    }
+
+    MONTHS.iter()
+        .position(|m| m.starts_with(word))
+        .map(|i| i as u32 + 1)
+}
+
+// HELPER: Day of the month, with or without an ordinal suffix ("20", "20th")
+fn parse_day (word: &str) -> Option<u32> {
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &word[digits.len()..];
+
+    if !matches!(suffix, "" | "st" | "nd" | "rd" | "th") {
+        return None;
+    }
+
+    digits.parse().ok().filter(|d| (1..=31).contains(d))
+}
+
+// HELPER: The next time (from today on) that a day and month come around
+fn upcoming (today: NaiveDate, month: u32, day: u32) -> Option<NaiveDate> {
+    match NaiveDate::from_ymd_opt(today.year(), month, day) {
+        Some(date) if date >= today => Some(date),
+        _ => NaiveDate::from_ymd_opt(today.year() + 1, month, day),
+    }
+}
+
+fn days_from_monday (date: NaiveDate) -> i64 {
+    date.weekday().num_days_from_monday() as i64
+}
+
+fn first_of_month (date: NaiveDate) -> Option<NaiveDate> {
+    date.with_day(1)
+}
+
+fn last_of_month (date: NaiveDate) -> Option<NaiveDate> {
+    first_of_month(date)?.checked_add_months(Months::new(1))?.pred_opt()
+}
+
+// SECTION - Display
+
+// Pending tasks due within this many days are "due soon"
+const DUE_SOON_DAYS: i64 = 3;
+
+// Display settings of this run, read from the config once
+static SETTINGS: OnceLock<DateSettings> = OnceLock::new();
+
+// How dates are shown (`date_style` in the config)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DateStyle {
+    // "20th August, 2025  16:00", or the `date_format` pattern
+    #[default]
+    Absolute,
+    // "2025-08-20 16:00"
+    Iso,
+    // "in 2 days", "3h overdue"
+    Relative,
+}
+
+// Clock used for times (`clock` in the config)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Clock {
+    #[default]
+    #[serde(rename = "24h")]
+    H24,
+    #[serde(rename = "12h")]
+    H12,
+}
+
+// How close a pending task is to its due date
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Urgency {
+    Overdue,
+    Today,
+    Soon,
+    Later,
+}
+
+struct DateSettings {
+    style: DateStyle,
+    pattern: Option<String>,
+    clock: Clock,
+    zone: Option<Tz>,
+    locale: Option<Locale>,
+}
+
+// HELPER: Display settings from the config. Unknown timezones and locales are warned about and ignored
+fn settings () -> &'static DateSettings {
+    SETTINGS.get_or_init(|| {
+        let config = load_config().unwrap_or_default();
+
+        let zone = config.timezone.as_deref().and_then(|name| match name.parse::<Tz>() {
+            Ok(zone) => Some(zone),
+            Err(_) => {
+                eprintln!("{} Unknown timezone \"{}\" in the config, showing dates in the system timezone", "".yellow(), name);
+                None
+            },
+        });
+
+        let locale = config.locale.as_deref().and_then(|name| match name.parse::<Locale>() {
+            Ok(locale) => Some(locale),
+            Err(_) => {
+                eprintln!("{} Unknown locale \"{}\" in the config, showing dates in English", "".yellow(), name);
+                None
+            },
+        });
+
+        DateSettings {
+            style: config.date_style.unwrap_or_default(),
+            pattern: config.date_format.filter(|p| !p.trim().is_empty()),
+            clock: config.clock.unwrap_or_default(),
+            zone,
+            locale,
+        }
+    })
+}
+
+pub fn date_style () -> DateStyle {
+    settings().style
+}
+
+// Move a timestamp into the display timezone (configured, else the system's)
+fn in_zone (dt: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+    match settings().zone {
+        Some(zone) => dt.with_timezone(&zone).fixed_offset(),
+        None => dt.with_timezone(&Local).fixed_offset(),
+    }
+}
+
+// Write a timestamp the configured way. `done` is given for due dates, to word relative ones
+pub fn show_date (dt: DateTime<FixedOffset>, done: Option<bool>) -> String {
+    match settings().style {
+        DateStyle::Absolute => absolute_date(dt),
+        DateStyle::Iso => iso_date(dt),
+        DateStyle::Relative => relative_date(dt, done),
+    }
+}
+
+// Compact version of `show_date`, for table cells
+pub fn show_short_date (dt: DateTime<FixedOffset>, done: Option<bool>) -> String {
+    match settings().style {
+        DateStyle::Relative => relative_date(dt, done),
+        _ => iso_date(dt),
+    }
+}
+
+// "20th August, 2025  16:00" (or localized, or the configured pattern)
+pub fn absolute_date (dt: DateTime<FixedOffset>) -> String {
+    let settings = settings();
+    let dt = in_zone(dt);
+
+    let time = match (settings.clock, settings.locale) {
+        (Clock::H24, _) => "%H:%M".to_string(),
+        // Many locales have no AM/PM words (%p comes out empty), so those get the English ones
+        (Clock::H12, Some(locale)) if dt.format_localized("%p", locale).to_string().trim().is_empty() => format!("%-I:%M {}", dt.format("%p")),
+        (Clock::H12, _) => "%-I:%M %p".to_string(),
+    };
+
+    let pattern = match (&settings.pattern, settings.locale) {
+        (Some(pattern), _) => pattern.clone(),
+        (None, Some(_)) => format!("%-d %B %Y  {}", time),
+        // English day numbers get their ordinal suffix, which strftime doesn't have
+        (None, None) => format!("%-d{} %B, %Y  {}", ordinal_suffix(dt.day()), time),
+    };
+
+    // Invalid patterns fail while being written, rather than when parsed
+    let mut out = String::new();
+    let written = match settings.locale {
+        Some(locale) => write!(out, "{}", dt.format_localized(&pattern, locale)),
+        None => write!(out, "{}", dt.format(&pattern)),
+    };
+
+    match written {
+        Ok(()) => out,
+        Err(_) => iso_date(dt),
+    }
+}
+
+// "2025-08-20 16:00"
+pub fn iso_date (dt: DateTime<FixedOffset>) -> String {
+    let time = match settings().clock {
+        Clock::H24 => "%H:%M",
+        Clock::H12 => "%I:%M %p",
+    };
+
+    in_zone(dt).format(&format!("%Y-%m-%d {}", time)).to_string()
+}
+
+// "in 2 days", "3h overdue" for pending tasks, "2 days ago" for anything else in the past
+pub fn relative_date (dt: DateTime<FixedOffset>, done: Option<bool>) -> String {
+    let left = dt.with_timezone(&Utc) - Utc::now();
+
+    // Rounded to the nearest unit, so a task due "in 2h" doesn't show "in 1h" a second later
+    let span = |d: Duration| {
+        let secs = d.num_seconds().abs();
+        let (days, hours, minutes) = ((secs + 43_200) / 86_400, (secs + 1_800) / 3_600, (secs + 30) / 60);
+
+        if hours >= 24 {
+            format!("{} day{}", days, if days == 1 { "" } else { "s" })
+        } else if minutes >= 60 {
+            format!("{}h", hours)
+        } else {
+            format!("{}m", minutes.max(1))
+        }
+    };
+
+    match (left < Duration::zero(), done) {
+        (false, _) => format!("in {}", span(left)),
+        (true, Some(false)) => format!("{} overdue", span(left)),
+        (true, _) => format!("{} ago", span(left)),
+    }
+}
+
+// How urgent a due date is. Done tasks have no urgency
+pub fn urgency (due: DateTime<FixedOffset>, done: bool) -> Option<Urgency> {
+    if done {
+        return None;
+    }
+
+    let now = in_zone(Utc::now().fixed_offset());
+    let due = in_zone(due);
+
+    Some(if due < now {
+        Urgency::Overdue
+    } else if due.date_naive() == now.date_naive() {
+        Urgency::Today
+    } else if due - now <= Duration::days(DUE_SOON_DAYS) {
+        Urgency::Soon
+    } else {
+        Urgency::Later
+    })
+}
+
+// Color a due date by how urgent it is
+pub fn paint_due (text: &str, urgency: Option<Urgency>) -> ColoredString {
+    match urgency {
+        Some(Urgency::Overdue) => text.red().bold(),
+        Some(Urgency::Today) => text.yellow(),
+        Some(Urgency::Soon) => text.magenta(),
+        Some(Urgency::Later) | None => text.blue(),
+    }
+}
+
+// HELPER: Add suffices like 1st, 2nd, 4th, etc.
+fn ordinal_suffix (n: u32) -> &'static str {
+    match n {
+        11..=13 => "th",
+        _ => match n % 10 {
+            1 => "st",
+            2 => "nd",
+            3 => "rd",
+            _ => "th",
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Wednesday, 13th August 2025, 10:30
+    fn now () -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 8, 13).unwrap().and_hms_opt(10, 30, 0).unwrap()
+    }
+
+    fn check (cases: &[(&str, &str)], default_hour: u32) {
+        for (input, expected) in cases {
+            let parsed = parse_natural(input, now(), default_hour).map(|d| d.format("%Y-%m-%d %H:%M").to_string());
+            assert_eq!(parsed.as_deref(), Some(*expected), "parsing {:?}", input);
+        }
+    }
+
+    #[test]
+    fn days_and_times () {
+        check(&[
+            ("today 16:00", "2025-08-13 16:00"),
+            ("Tomorrow 9:15", "2025-08-14 09:15"),
+            ("friday", "2025-08-15 09:00"),
+            ("fri 5pm", "2025-08-15 17:00"),
+            ("friday at 5:30 pm", "2025-08-15 17:30"),
+            ("wednesday", "2025-08-20 09:00"),
+            ("monday 08:00", "2025-08-18 08:00"),
+            ("next friday", "2025-08-22 09:00"),
+            ("next monday 10:00", "2025-08-18 10:00"),
+            ("next week", "2025-08-18 09:00"),
+            ("next month", "2025-09-01 09:00"),
+            ("tomorrow noon", "2025-08-14 12:00"),
+            ("tomorrow 12am", "2025-08-14 00:00"),
+            ("tomorrow 12pm", "2025-08-14 12:00"),
+        ], 9);
+    }
+
+    #[test]
+    fn times_alone () {
+        check(&[
+            ("5pm", "2025-08-13 17:00"),
+            ("17:45", "2025-08-13 17:45"),
+            ("9am", "2025-08-14 09:00"),
+            ("10:30", "2025-08-14 10:30"),
+            ("noon", "2025-08-13 12:00"),
+        ], 9);
+    }
+
+    #[test]
+    fn offsets () {
+        check(&[
+            ("in 2h", "2025-08-13 12:30"),
+            ("in 90 minutes", "2025-08-13 12:00"),
+            ("in 30m", "2025-08-13 11:00"),
+            ("in 1 hour", "2025-08-13 11:30"),
+            ("in 3 days", "2025-08-16 09:00"),
+            ("in 3d 18:00", "2025-08-16 18:00"),
+            ("in 2 weeks", "2025-08-27 09:00"),
+            ("in 1 month", "2025-09-13 09:00"),
+        ], 9);
+    }
+
+    #[test]
+    fn end_of_period () {
+        check(&[
+            ("eod", "2025-08-13 23:59"),
+            ("end of day", "2025-08-13 23:59"),
+            ("eow", "2025-08-17 23:59"),
+            ("end of week", "2025-08-17 23:59"),
+            ("end of month", "2025-08-31 23:59"),
+        ], 9);
+    }
+
+    #[test]
+    fn explicit_dates () {
+        check(&[
+            ("2025-08-20", "2025-08-20 09:00"),
+            ("2025-08-20 17:30", "2025-08-20 17:30"),
+            ("2025-08-20T17:30", "2025-08-20 17:30"),
+            ("20/08/2025 9:15", "2025-08-20 09:15"),
+            ("20-08-2025", "2025-08-20 09:00"),
+            ("20/08", "2025-08-20 09:00"),
+            ("01/08", "2026-08-01 09:00"),
+            ("aug 20", "2025-08-20 09:00"),
+            ("20 aug", "2025-08-20 09:00"),
+            ("August 20 5pm", "2025-08-20 17:00"),
+            ("aug 1", "2026-08-01 09:00"),
+            ("20th august 2026 9:15", "2026-08-20 09:15"),
+            ("sept 3rd", "2025-09-03 09:00"),
+        ], 9);
+    }
+
+    #[test]
+    fn default_hour () {
+        check(&[
+            ("friday", "2025-08-15 17:00"),
+            ("in 3 days", "2025-08-16 17:00"),
+            ("aug 20", "2025-08-20 17:00"),
+            ("friday 8am", "2025-08-15 08:00"),
+        ], 17);
+    }
+
+    #[test]
+    fn rejects_nonsense () {
+        for input in ["", "someday", "31/02/2025", "13pm", "0am", "25:00", "5", "in 3 lightyears", "friday 5pm extra", "next", "next decade", "aug 32", "in -2 days", "20%08%2025"] {
+            assert_eq!(parse_natural(input, now(), 9), None, "parsing {:?}", input);
+        }
+    }
+}
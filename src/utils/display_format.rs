@@ -1,8 +1,12 @@
+use super::credentials::CredentialError;
+use super::error::ApiError;
+use super::output::{emit, emit_error, is_machine, mark_failed};
 use super::types::{PriorityType, Project, Task};
 use colored::*;
-use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone, Timelike, Utc, Weekday};
+use super::dates::{absolute_date, date_style, paint_due, relative_date, show_date, urgency, DateStyle};
+use chrono::DateTime;
 use chrono::format::ParseError;
-use serde_json::Value;
+use serde_json::{json, Value};
 
 // Pretty print TASKS
 pub fn print_task (task: &Task, show_proj: bool) -> Result<(), Box<dyn std::error::Error>> {
@@ -16,14 +20,13 @@ pub fn print_task (task: &Task, show_proj: bool) -> Result<(), Box<dyn std::erro
         None => " ".white().dimmed(),
     };
 
-    let due_str = task.due.as_deref().unwrap_or("");
-    let due = format_pretty_date(due_str)?;
+    let due = due_label(task);
 
     if show_proj {
         let pid = format!("<{}>", task.project_id).blue();
-        println!("{} {} {} {:<35} {} {}\n", id, status, pid, task.title, priority_dot, due.blue());
+        println!("{} {} {} {:<35} {} {}\n", id, status, pid, task.title, priority_dot, due);
     } else {
-        println!("{} {} {:<35} {} {}\n", id, status, task.title, priority_dot, due.blue());
+        println!("{} {} {:<35} {} {}\n", id, status, task.title, priority_dot, due);
     }
 
     if let Some(desc) = &task.description {
@@ -33,100 +36,166 @@ pub fn print_task (task: &Task, show_proj: bool) -> Result<(), Box<dyn std::erro
     Ok(())
 }
 
+// Pretty print one TASK with every field, and the name of its project if known
+pub fn print_task_detail (task: &Task, project: Option<&Project>) -> Result<(), Box<dyn std::error::Error>> {
+    let status = if task.is_done { "Done".green() } else { "Pending".red() };
+
+    let priority = match task.priority {
+        Some(PriorityType::High) => format!("{} High", "".red()),
+        Some(PriorityType::Medium) => format!("{} Medium", "".yellow()),
+        Some(PriorityType::Low) => format!("{} Low", "".green()),
+        None => "None".dimmed().to_string(),
+    };
+
+    let project = match project {
+        Some(p) => format!("{} {}", format!("[{}]", p.id).yellow(), p.name),
+        None => format!("[{}]", task.project_id).yellow().to_string(),
+    };
+
+    // The date, with how long is left (or the date itself, when dates are shown relative)
+    let due = match task.due.as_deref().map(|d| (d, DateTime::parse_from_rfc3339(d))) {
+        Some((_, Ok(dt))) => {
+            let aside = match date_style() {
+                DateStyle::Relative => absolute_date(dt),
+                _ => relative_date(dt, Some(task.is_done)),
+            };
+            format!("{}  {}", due_label(task), format!("({})", aside).dimmed())
+        },
+        Some((d, Err(_))) => d.to_string(),
+        None => "No due date".dimmed().to_string(),
+    };
+
+    println!("{} {}", format!("[{}]", task.id).yellow(), task.title.bold());
+    println!("──────────────────────────────\n");
+    println!("{} {}", format!("{:<10}", "Status:").blue(), status);
+    println!("{} {}", format!("{:<10}", "Priority:").blue(), priority);
+    println!("{} {}", format!("{:<10}", "Project:").blue(), project);
+    println!("{} {}", format!("{:<10}", "Due:").blue(), due);
+    println!("{} {}", format!("{:<10}", "Created:").blue(), format_pretty_date(&task.created_at)?);
+
+    if let Some(desc) = task.description.as_deref().filter(|d| !d.trim().is_empty()) {
+        println!("\n{}", "Description:".blue());
+
+        // Wrap to the terminal, keeping the author's line breaks
+        let width = terminal_size::terminal_size().map(|(w, _)| w.0 as usize).unwrap_or(80).clamp(20, 100) - 2;
+        for line in desc.lines() {
+            for wrapped in wrap(line, width) {
+                println!("  {}", wrapped);
+            }
+        }
+    }
+
+    Ok(())
+}
+
 // Pretty print PROJECTS
 pub fn print_project (project: Project) {
     let pid = format!("[{}]", project.id).yellow();
     println!("{} {} {:<35}\n", " ".yellow(), pid, project.name);
 }
 
-// Pretty print ERRORS
-pub fn log_err (res: Value) {
-    if let Some(msg) = res.get("message").and_then(|m| m.as_str()) {
-        println!("{} {}", " ".red(), msg.red());
-    } else if let Some(err) = res.get("error").and_then(|e| e.as_str()) {
-        println!("{} The following error occured: {}", " ".red(), err.red());
+// Pretty print a REQUEST held back by --dry-run
+pub fn print_request (method: &str, url: &str, payload: Option<&Value>) {
+    if is_machine() {
+        emit(&json!({ "dry_run": { "method": method, "url": url, "payload": payload } }));
+        return;
+    }
+
+    // Built as one string, so requests sent concurrently don't interleave
+    let mut out = format!("\n{} {} {} {}", "".cyan(), "Dry run, not sent:".cyan(), method.bold(), url);
+    if let Some(body) = payload.and_then(|p| serde_json::to_string_pretty(p).ok()) {
+        for line in body.lines() {
+            out.push_str("\n    ");
+            out.push_str(line);
+        }
+    }
+
+    println!("{}", out);
+}
+
+// Pretty print ERRORS, followed by a hint on what to do about them
+pub fn log_err (err: &ApiError) {
+    let hint = err_hint(err);
+
+    if is_machine() {
+        emit_error(err.kind(), &err.to_string(), hint.as_deref());
+        return;
+    }
+
+    mark_failed();
+    println!("{} {}", " ".red(), err.to_string().red());
+
+    if let Some(hint) = hint {
+        println!("  {}", hint);
     }
 }
 
-// Custom formatter for DATETIME
-pub fn natural_to_datetime (input: &str) -> Option<String> {
-    let input = input.trim().to_lowercase();
-    let parts: Vec<&str> = input.split_whitespace().collect();
-    if parts.len() != 2 {
-        return None;
+// Report a command that couldn't be carried out (with a JSON error on stderr for --output json)
+pub fn log_failure (message: String) {
+    if is_machine() {
+        emit_error("invalid", &message, None);
+        return;
     }
 
-    let day_or_date = parts[0];
-    let time_str = parts[1];
-    let time = NaiveTime::parse_from_str(time_str, "%H:%M").ok()?;
-
-    let today = Local::now().date_naive();
-
-    let due_date = match day_or_date {
-        "today" => today,
-        "tomorrow" => today + Duration::days(1),
-        _ => {
-            if let Ok(date) = NaiveDate::parse_from_str(day_or_date, "%d-%d-%Y") {
-                date
-            } else {
-                let weekday = match day_or_date {
-                    "monday" => Weekday::Mon,
-                    "tuesday" => Weekday::Tue,
-                    "wednesday" => Weekday::Wed,
-                    "thursday" => Weekday::Thu,
-                    "friday" => Weekday::Fri,
-                    "saturday" => Weekday::Sat,
-                    "sunday" => Weekday::Sun,
-                    _ => return None,
-                };
-
-                let cur_weekday = today.weekday();
-                let mut days_ahead = (weekday.num_days_from_monday() + 7 - cur_weekday.num_days_from_monday()) % 7;
-
-                if days_ahead == 0 {
-                    days_ahead = 7;
-                }
-
-                today + Duration::days(days_ahead as i64)
-            }
-        }
-    };
+    mark_failed();
+    println!("\n{} {}", "".red(), message);
+}
 
-    let local_dt = Local.with_ymd_and_hms(due_date.year(), due_date.month(), due_date.day(), time.hour(), time.minute(), 0).unwrap();
-    let utc_dt: DateTime<Utc> = local_dt.with_timezone(&Utc);
+// HELPER: What the user can do about an error
+fn err_hint (err: &ApiError) -> Option<String> {
+    let hint = match err {
+        ApiError::NotLoggedIn => format!("Log in to your account by running {}", "cues login".yellow()),
+        ApiError::Credentials(CredentialError::Decrypt) => format!("Check the passphrase (or {}), or log in again with {}", "CUES_PASSPHRASE".yellow(), "cues login".yellow()),
+        ApiError::Credentials(CredentialError::Keyring(_)) => format!("If the system keyring is unavailable, log in with {}", "cues login --credential-store encrypted-file".yellow()),
+        ApiError::Credentials(_) => "Check the permissions of the Cues config directory.".to_string(),
+        ApiError::Network(_) => format!("Check your internet connection, or the backend URL set via {} or {}.", "--api-url".yellow(), "CUES_API_URL".yellow()),
+        ApiError::Timeout => "The server may be starting up or overloaded. Try again in a moment.".to_string(),
+        ApiError::Unauthorized(_) => format!("Log in again by running {}", "cues login".yellow()),
+        ApiError::NotFound(_) => format!("Check the id using {} or {}", "cues tasks".yellow(), "cues projects".yellow()),
+        ApiError::Forbidden(_) | ApiError::Validation(_) | ApiError::Invalid(_) | ApiError::DryRun => return None,
+        ApiError::Server { .. } => "This is a problem on the server's side. Try again later.".to_string(),
+        ApiError::Decode(_) => format!("Make sure the backend URL ({}) points to a Cues server.", "--api-url".yellow()),
+    };
 
-    Some(utc_dt.to_rfc3339())
+    Some(hint)
 }
 
-// HELPER: Add suffices like 1st, 2nd, 4th, etc.
-fn ordinal_suffix(n: u32) -> &'static str {
-    match n {
-        11 | 12 | 13 => "th",
-        _ => match n % 10 {
-            1 => "st",
-            2 => "nd",
-            3 => "rd",
-            _ => "th",
-        },
+// HELPER: Split a line into pieces of at most `width` characters, at spaces where possible
+fn wrap (line: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+
+    for word in line.split_whitespace() {
+        let cur = lines.last_mut().unwrap();
+        if !cur.is_empty() && cur.chars().count() + 1 + word.chars().count() > width {
+            lines.push(String::new());
+        }
+
+        let cur = lines.last_mut().unwrap();
+        if !cur.is_empty() {
+            cur.push(' ');
+        }
+        cur.push_str(word);
     }
+
+    lines
 }
 
-// Normalize date format
+// Normalize date format (the configured display style)
 pub fn format_pretty_date (input: &str) -> Result<String, ParseError> {
     if input.trim().is_empty() {
         return Ok("No due date".to_string());
     }
 
-    let dt_utc: DateTime<chrono::FixedOffset> = input.parse()?;
-    let dt: DateTime<Local> = dt_utc.with_timezone(&Local);
-
-    let day = dt.day();
-    let suffix = ordinal_suffix(day);
-    let month = dt.format("%B").to_string();
-    let year = dt.year();
-    let time = dt.format("%H:%M").to_string();
+    Ok(show_date(DateTime::parse_from_rfc3339(input)?, None))
+}
 
-    Ok(format!("{}{} {}, {}", day, suffix, month, year) + "    " + &time)
+// HELPER: Due date of a task as shown in listings, colored by urgency
+fn due_label (task: &Task) -> ColoredString {
+    match task.due.as_deref().map(|d| (d, DateTime::parse_from_rfc3339(d))) {
+        Some((_, Ok(dt))) => paint_due(&show_date(dt, Some(task.is_done)), urgency(dt, task.is_done)),
+        Some((d, Err(_))) => d.blue(),
+        None => "No due date".blue(),
+    }
 }
 
 
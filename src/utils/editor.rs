@@ -0,0 +1,273 @@
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+
+use super::dates::natural_to_datetime;
+use super::session::env_var;
+use super::types::{PriorityType, Task};
+
+// Line around the task fields, before the description
+const DELIMITER: &str = "+++";
+
+// A task as written in the editor: TOML fields between +++ lines, then the description
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskDraft {
+    pub title: String,
+    pub priority: Option<PriorityType>,
+    // As the user wrote it ("friday 5pm"), parsed when the task is saved
+    pub due: Option<String>,
+    pub description: Option<String>,
+}
+
+// Problem found in an edited document, with the line it's on (1-based)
+pub struct DraftError {
+    pub line: usize,
+    pub message: String,
+}
+
+// Fields between the +++ lines
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FrontMatter {
+    title: String,
+    #[serde(default)]
+    priority: String,
+    #[serde(default)]
+    due: String,
+}
+
+impl TaskDraft {
+    // Draft of an existing task, with the due date in local time
+    pub fn from_task (task: &Task) -> Self {
+        let due = task.due.as_deref().map(|d| match DateTime::parse_from_rfc3339(d) {
+            Ok(dt) => dt.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+            Err(_) => d.to_string(),
+        });
+
+        TaskDraft {
+            // Trimmed like `parse` trims them, so untouched fields compare equal
+            title: task.title.trim().to_string(),
+            priority: task.priority.clone(),
+            due,
+            description: task.description.as_deref().map(str::trim).filter(|d| !d.is_empty()).map(str::to_string),
+        }
+    }
+
+    // Write the draft as a document to edit
+    pub fn render (&self) -> String {
+        let quote = |s: &str| toml::Value::String(s.to_string()).to_string();
+        let priority = self.priority.as_ref().map(|p| format!("{:?}", p).to_lowercase()).unwrap_or_default();
+
+        format!(
+            "{d}\n# Save and close the editor when done. Empty values remove a field.\n# priority: high, medium or low. due: eg \"friday 5pm\", \"in 3 days\" or \"2026-08-20 09:00\"\ntitle = {}\npriority = {}\ndue = {}\n{d}\n{}\n",
+            quote(&self.title),
+            quote(&priority),
+            quote(self.due.as_deref().unwrap_or("")),
+            self.description.as_deref().unwrap_or(""),
+            d = DELIMITER,
+        )
+    }
+
+    // Read an edited document back. Every problem found is returned, with its line
+    pub fn parse (text: &str) -> Result<Self, Vec<DraftError>> {
+        let error = |line: usize, message: &str| vec![DraftError { line, message: message.to_string() }];
+        let lines: Vec<&str> = text.lines().collect();
+
+        if lines.first().map(|l| l.trim()) != Some(DELIMITER) {
+            return Err(error(1, "The document must start with a +++ line"));
+        }
+
+        let Some(end) = lines.iter().skip(1).position(|l| l.trim() == DELIMITER).map(|i| i + 1) else {
+            return Err(error(1, "Missing the +++ line after the task fields"));
+        };
+
+        // The fields start on the second line of the document
+        let front = lines[1..end].join("\n");
+        let fields: FrontMatter = toml::from_str(&front).map_err(|e| {
+            let line = e.span().map(|span| front[..span.start].matches('\n').count() + 2).unwrap_or(1);
+            error(line, e.message().trim())
+        })?;
+
+        let line_of = |key: &str| lines[1..end].iter()
+            .position(|l| l.trim_start().strip_prefix(key).is_some_and(|rest| rest.trim_start().starts_with('=')))
+            .map(|i| i + 2)
+            .unwrap_or(1);
+
+        let mut errors = Vec::new();
+
+        let title = fields.title.trim().to_string();
+        if title.is_empty() {
+            errors.push(DraftError { line: line_of("title"), message: "The title can't be empty".to_string() });
+        }
+
+        let priority = match fields.priority.trim().to_lowercase().as_str() {
+            "" => None,
+            "high" => Some(PriorityType::High),
+            "medium" => Some(PriorityType::Medium),
+            "low" => Some(PriorityType::Low),
+            other => {
+                errors.push(DraftError { line: line_of("priority"), message: format!("Unknown priority \"{}\" (use high, medium or low)", other) });
+                None
+            },
+        };
+
+        let due = Some(fields.due.trim().to_string()).filter(|d| !d.is_empty());
+        if let Some(phrase) = due.as_deref().filter(|d| natural_to_datetime(d).is_none()) {
+            errors.push(DraftError { line: line_of("due"), message: format!("Invalid due date \"{}\"", phrase) });
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let description = lines[end + 1..].join("\n").trim().to_string();
+
+        Ok(TaskDraft {
+            title,
+            priority,
+            due,
+            description: Some(description).filter(|d| !d.is_empty()),
+        })
+    }
+}
+
+// Write a draft to a new temporary file, named after what is edited, that only the user can read.
+// The file must not exist yet, so nothing planted at that path (like a symlink) is written through
+pub fn create_draft (name: &str, contents: &str) -> io::Result<PathBuf> {
+    for attempt in 0..100 {
+        let path = env::temp_dir().join(format!("cues-{}-{}-{}.md", name, process::id(), attempt));
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        match options.open(&path) {
+            Ok(mut file) => {
+                file.write_all(contents.as_bytes())?;
+                return Ok(path);
+            },
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::AlreadyExists, "Couldn't create a temporary file for the editor"))
+}
+
+// Open a file in $VISUAL or $EDITOR (vi or notepad when neither is set), and wait for it to close
+pub fn open_editor (path: &Path) -> io::Result<()> {
+    let editor = env_var("VISUAL")
+        .or_else(|| env_var("EDITOR"))
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+
+    // Editors are often set with arguments, like "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .map_err(|e| io::Error::new(e.kind(), format!("Couldn't start the editor ({}): {}", program, e)))?;
+
+    if !status.success() {
+        return Err(io::Error::other(format!("The editor ({}) exited with {}", program, status)));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draft () -> TaskDraft {
+        TaskDraft {
+            title: "Write \"release\" notes".to_string(),
+            priority: Some(PriorityType::High),
+            due: Some("2026-08-20 09:00".to_string()),
+            description: Some("line one\n\nline three".to_string()),
+        }
+    }
+
+    // The line each problem was reported on
+    fn error_lines (text: &str) -> Vec<usize> {
+        match TaskDraft::parse(text) {
+            Ok(_) => panic!("parsing {:?} should fail", text),
+            Err(errors) => errors.iter().map(|e| e.line).collect(),
+        }
+    }
+
+    #[test]
+    fn round_trip () {
+        let draft = draft();
+        assert_eq!(TaskDraft::parse(&draft.render()).ok(), Some(draft));
+
+        let empty = TaskDraft { title: "Title".to_string(), priority: None, due: None, description: None };
+        assert_eq!(TaskDraft::parse(&empty.render()).ok(), Some(empty));
+    }
+
+    #[test]
+    fn untouched_task_is_unchanged () {
+        let task = Task {
+            id: 1,
+            title: "Title".to_string(),
+            description: Some("line one\n".to_string()),
+            due: None,
+            priority: None,
+            project_id: 1,
+            is_done: false,
+            created_at: "2026-08-13T10:30:00Z".to_string(),
+        };
+
+        let original = TaskDraft::from_task(&task);
+        assert_eq!(TaskDraft::parse(&original.render()).ok(), Some(original));
+    }
+
+    // The rendered draft, with the line of `key` replaced
+    fn with_line (key: &str, line: &str) -> String {
+        draft().render().lines()
+            .map(|l| if l.starts_with(&format!("{} =", key)) { line } else { l })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn error_lines_point_at_the_field () {
+        // render() puts title, priority and due on lines 4, 5 and 6
+        let cases = [
+            (with_line("title", "title = \"unclosed"), vec![4]),
+            (with_line("title", "title = \" \""), vec![4]),
+            (with_line("priority", "priority = \"urgent\""), vec![5]),
+            (with_line("priority", "priority = 3"), vec![5]),
+            (with_line("due", "due = \"someday\""), vec![6]),
+            (with_line("due", "due = \"2026-02-30\""), vec![6]),
+            (with_line("due", "due = \"\"\ncolour = \"red\""), vec![7]),
+            (with_line("priority", "priority = \"urgent\"").replace("due = \"2026-08-20 09:00\"", "due = \"someday\""), vec![5, 6]),
+        ];
+
+        for (text, expected) in cases {
+            assert_ne!(text.trim(), draft().render().trim(), "the case should change the document");
+            assert_eq!(error_lines(&text), expected, "parsing {:?}", text);
+        }
+    }
+
+    #[test]
+    fn missing_delimiters () {
+        let text = draft().render();
+
+        assert_eq!(error_lines(text.strip_prefix("+++\n").unwrap()), vec![1]);
+        // Without the closing line, the description would be read as fields
+        assert_eq!(error_lines(&text.replacen("\n+++\n", "\n", 1)), vec![1]);
+        assert_eq!(error_lines(""), vec![1]);
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+
+use super::credentials::CredentialError;
+
+// Errors returned by the API client
+#[derive(Debug)]
+pub enum ApiError {
+    // No stored credentials, the user has to log in first
+    NotLoggedIn,
+    // Stored credentials exist but couldn't be read or written
+    Credentials(CredentialError),
+    // Server could not be reached (DNS, refused connection, TLS, ...)
+    Network(String),
+    // Server did not answer in time
+    Timeout,
+    // Access token or credentials rejected (401)
+    Unauthorized(Option<String>),
+    // Logged in, but not allowed to do this (403)
+    Forbidden(Option<String>),
+    // Requested task/project does not exist (404)
+    NotFound(Option<String>),
+    // Backend rejected the request (other 4xx), with its message
+    Validation(String),
+    // Backend failed to handle the request (5xx)
+    Server { status: u16, message: Option<String> },
+    // Response body was not what the client expected
+    Decode(String),
+    // Input given on the command line couldn't be understood (nothing was sent)
+    Invalid(String),
+    // Request was printed instead of sent (--dry-run)
+    DryRun,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::NotLoggedIn => write!(f, "You may not be logged in"),
+            ApiError::Credentials(e) => write!(f, "{}", e),
+            ApiError::Network(e) => write!(f, "Could not reach the Cues server: {}", e),
+            ApiError::Timeout => write!(f, "The Cues server took too long to respond"),
+            ApiError::Unauthorized(Some(msg)) => write!(f, "{}", msg),
+            ApiError::Unauthorized(None) => write!(f, "Your session is invalid or has expired"),
+            ApiError::Forbidden(Some(msg)) => write!(f, "{}", msg),
+            ApiError::Forbidden(None) => write!(f, "You don't have permission to do that"),
+            ApiError::NotFound(Some(msg)) => write!(f, "{}", msg),
+            ApiError::NotFound(None) => write!(f, "The requested item could not be found"),
+            ApiError::Validation(msg) => write!(f, "{}", msg),
+            ApiError::Server { status, message: Some(msg) } => write!(f, "Server error ({}): {}", status, msg),
+            ApiError::Server { status, message: None } => write!(f, "Server error ({})", status),
+            ApiError::Decode(e) => write!(f, "Unexpected response from server: {}", e),
+            ApiError::Invalid(msg) => write!(f, "{}", msg),
+            ApiError::DryRun => write!(f, "Nothing was sent (dry run)"),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl ApiError {
+    // Short machine-readable name of the error, for --output json
+    pub fn kind (&self) -> &'static str {
+        match self {
+            ApiError::NotLoggedIn => "not_logged_in",
+            ApiError::Credentials(_) => "credentials",
+            ApiError::Network(_) => "network",
+            ApiError::Timeout => "timeout",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Validation(_) => "validation",
+            ApiError::Server { .. } => "server",
+            ApiError::Decode(_) => "decode",
+            ApiError::Invalid(_) => "invalid",
+            ApiError::DryRun => "dry_run",
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from (e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            ApiError::Timeout
+        } else if e.is_decode() {
+            ApiError::Decode(e.to_string())
+        } else {
+            ApiError::Network(e.to_string())
+        }
+    }
+}
+
+impl From<CredentialError> for ApiError {
+    fn from (e: CredentialError) -> Self {
+        match e {
+            CredentialError::Missing => ApiError::NotLoggedIn,
+            e => ApiError::Credentials(e),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from (e: serde_json::Error) -> Self {
+        ApiError::Decode(e.to_string())
+    }
+}
@@ -0,0 +1,122 @@
+use chrono::{DateTime, Datelike, Duration, Local};
+use clap::Args;
+
+use crate::cli::Priority;
+use super::dates::natural_to_datetime;
+use super::types::{PriorityType, Task};
+
+// Filters narrowing down a task listing. All of the given filters have to match
+#[derive(Args, Debug, Default, Clone)]
+pub struct TaskFilter {
+    #[arg(long, help="Only tasks not done yet", conflicts_with="done")]
+    pub pending: bool,
+
+    #[arg(long, help="Only tasks marked as done")]
+    pub done: bool,
+
+    #[arg(long, value_enum, help="Only tasks with this priority")]
+    pub priority: Option<Priority>,
+
+    #[arg(long, value_name="WHEN", help="Only tasks due before this (eg, \"friday 18:00\")")]
+    pub due_before: Option<String>,
+
+    #[arg(long, value_name="WHEN", help="Only tasks due after this (eg, \"today 09:00\")")]
+    pub due_after: Option<String>,
+
+    #[arg(long, help="Only pending tasks past their due date")]
+    pub overdue: bool,
+
+    #[arg(long, help="Only tasks due today")]
+    pub today: bool,
+
+    #[arg(long, help="Only tasks due this week (Monday to Sunday)")]
+    pub this_week: bool,
+
+    #[arg(long, help="Only tasks without a due date")]
+    pub no_due: bool,
+
+    #[arg(long, value_name="TEXT", help="Only tasks whose title or description contains this (case-insensitive)")]
+    pub search: Option<String>,
+}
+
+// A TaskFilter with its date phrases parsed, ready to test tasks against
+pub struct TaskMatcher {
+    filter: TaskFilter,
+    before: Option<DateTime<Local>>,
+    after: Option<DateTime<Local>>,
+    now: DateTime<Local>,
+}
+
+impl TaskFilter {
+    // Whether any filter was given
+    pub fn is_active (&self) -> bool {
+        self.pending || self.done || self.priority.is_some() || self.due_before.is_some() || self.due_after.is_some()
+            || self.overdue || self.today || self.this_week || self.no_due || self.search.is_some()
+    }
+
+    // Parse the date filters. Fails with a message naming the flag whose date couldn't be understood
+    pub fn matcher (&self) -> Result<TaskMatcher, String> {
+        let parse = |flag: &str, phrase: &Option<String>| match phrase {
+            Some(p) => natural_to_datetime(p)
+                .and_then(|d| parse_due(&d))
+                .map(Some)
+                .ok_or_else(|| format!("Invalid date for {}: \"{}\"", flag, p)),
+            None => Ok(None),
+        };
+
+        Ok(TaskMatcher {
+            before: parse("--due-before", &self.due_before)?,
+            after: parse("--due-after", &self.due_after)?,
+            filter: self.clone(),
+            now: Local::now(),
+        })
+    }
+}
+
+impl TaskMatcher {
+    pub fn matches (&self, task: &Task) -> bool {
+        let f = &self.filter;
+        let due = task.due.as_deref().and_then(parse_due);
+        let today = self.now.date_naive();
+
+        // Date filters only match tasks that have a due date
+        let due_is = |pred: &dyn Fn(DateTime<Local>) -> bool| due.is_some_and(pred);
+
+        let week_start = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let week_end = week_start + Duration::days(6);
+
+        (!f.pending || !task.is_done)
+            && (!f.done || task.is_done)
+            && f.priority.as_ref().is_none_or(|p| same_priority(p, task.priority.as_ref()))
+            && self.before.is_none_or(|b| due_is(&|d| d < b))
+            && self.after.is_none_or(|a| due_is(&|d| d > a))
+            && (!f.overdue || (!task.is_done && due_is(&|d| d < self.now)))
+            && (!f.today || due_is(&|d| d.date_naive() == today))
+            && (!f.this_week || due_is(&|d| (week_start..=week_end).contains(&d.date_naive())))
+            && (!f.no_due || task.due.is_none())
+            && f.search.as_deref().is_none_or(|s| contains_text(task, s))
+    }
+}
+
+// HELPER: Due date of a task, in local time
+fn parse_due (due: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(due).ok().map(|d| d.with_timezone(&Local))
+}
+
+// HELPER: Compare the --priority flag to a task's priority
+fn same_priority (wanted: &Priority, actual: Option<&PriorityType>) -> bool {
+    matches!(
+        (wanted, actual),
+        (Priority::High, Some(PriorityType::High))
+            | (Priority::Medium, Some(PriorityType::Medium))
+            | (Priority::Low, Some(PriorityType::Low))
+    )
+}
+
+// HELPER: Case-insensitive search in the title and description
+fn contains_text (task: &Task, text: &str) -> bool {
+    let text = text.to_lowercase();
+
+    task.title.to_lowercase().contains(&text)
+        || task.description.as_deref().is_some_and(|d| d.to_lowercase().contains(&text))
+}
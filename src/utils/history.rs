@@ -0,0 +1,213 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use chrono::Utc;
+use colored::Colorize;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use super::config_path::{active_profile, get_config_dir};
+use super::types::Task;
+
+// How many operations are kept (older ones are dropped)
+const HISTORY_LIMIT: usize = 50;
+
+// Kind of change that was made to the tasks
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Add,
+    Edit,
+    Done,
+    Undone,
+    Toggle,
+    Move,
+    Delete,
+}
+
+impl Operation {
+    pub fn label (&self) -> &'static str {
+        match self {
+            Operation::Add => "add",
+            Operation::Edit => "edit",
+            Operation::Done => "done",
+            Operation::Undone => "undone",
+            Operation::Toggle => "toggle",
+            Operation::Move => "move",
+            Operation::Delete => "delete",
+        }
+    }
+}
+
+// One command that changed tasks, with what they looked like before it ran
+// (for `add`, the tasks as they were created)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    pub op: Operation,
+    pub profile: String,
+    pub at: String,
+    pub tasks: Vec<Task>,
+}
+
+// Returns path of the history file
+fn history_path () -> Option<PathBuf> {
+    get_config_dir().map(|dir| dir.join("history.json"))
+}
+
+// Every recorded operation (of all profiles), oldest first
+fn load_all () -> Vec<HistoryEntry> {
+    history_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+// Writes the whole history, keeping only the latest operations
+fn save_all (entries: &[HistoryEntry]) -> io::Result<()> {
+    let path = history_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Could not determine config directory"))?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let start = entries.len().saturating_sub(HISTORY_LIMIT);
+    let json = serde_json::to_string_pretty(&entries[start..])?;
+    fs::write(path, json)
+}
+
+// Operations of the active profile, oldest first
+pub fn load_history () -> Vec<HistoryEntry> {
+    let profile = active_profile();
+    load_all().into_iter().filter(|e| e.profile == profile).collect()
+}
+
+// Remember an operation so it can be undone. Failing to save only costs the undo, so it just warns
+pub fn record (op: Operation, tasks: Vec<Task>) {
+    if tasks.is_empty() {
+        return;
+    }
+
+    let mut entries = load_all();
+    entries.push(HistoryEntry {
+        op,
+        profile: active_profile(),
+        at: Utc::now().to_rfc3339(),
+        tasks,
+    });
+
+    if let Err(e) = save_all(&entries) {
+        eprintln!("{} Couldn't save the undo history: {}", "".yellow(), e);
+    }
+}
+
+// Replace the latest operation of the active profile with what is left of it to undo (None drops it).
+// Tasks that were recreated get new ids, given as (old, new), which older operations are pointed at
+pub fn settle_last (remaining: Option<HistoryEntry>, moved: &[(u32, u32)]) -> io::Result<()> {
+    let mut entries = load_all();
+    settle(&mut entries, &active_profile(), remaining, moved);
+    save_all(&entries)
+}
+
+// HELPER: `settle_last` on the given operations
+fn settle (entries: &mut Vec<HistoryEntry>, profile: &str, remaining: Option<HistoryEntry>, moved: &[(u32, u32)]) {
+    if let Some(i) = entries.iter().rposition(|e| e.profile == profile) {
+        match remaining {
+            Some(entry) => entries[i] = entry,
+            None => { entries.remove(i); },
+        }
+    }
+
+    for task in entries.iter_mut().filter(|e| e.profile == profile).flat_map(|e| e.tasks.iter_mut()) {
+        if let Some((_, new)) = moved.iter().find(|(old, _)| *old == task.id) {
+            task.id = *new;
+        }
+    }
+}
+
+// Payload that puts every editable field of a task back the way it was (including its project)
+pub fn restore_payload (task: &Task) -> Value {
+    json!({
+        "projectId": task.project_id,
+        "title": task.title,
+        "description": task.description,
+        "due": task.due,
+        "priority": task.priority,
+        "isDone": task.is_done,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task (id: u32) -> Task {
+        Task {
+            id,
+            title: format!("Task {}", id),
+            description: None,
+            due: None,
+            priority: None,
+            project_id: 1,
+            is_done: false,
+            created_at: "2026-10-18T10:00:00Z".to_string(),
+        }
+    }
+
+    fn entry (op: Operation, profile: &str, ids: &[u32]) -> HistoryEntry {
+        HistoryEntry {
+            op,
+            profile: profile.to_string(),
+            at: "2026-10-18T10:00:00Z".to_string(),
+            tasks: ids.iter().map(|&id| task(id)).collect(),
+        }
+    }
+
+    // (operation, profile, task ids) of every entry
+    fn summary (entries: &[HistoryEntry]) -> Vec<(Operation, &str, Vec<u32>)> {
+        entries.iter().map(|e| (e.op, e.profile.as_str(), e.tasks.iter().map(|t| t.id).collect())).collect()
+    }
+
+    #[test]
+    fn recreated_tasks_keep_their_history () {
+        let mut entries = vec![
+            entry(Operation::Edit, "default", &[4, 7]),
+            entry(Operation::Done, "work", &[4]),
+            entry(Operation::Toggle, "default", &[7, 9]),
+            entry(Operation::Delete, "default", &[4, 7]),
+            entry(Operation::Edit, "work", &[7]),
+        ];
+
+        // Undoing the delete recreated 4 as 20 and 7 as 21
+        settle(&mut entries, "default", None, &[(4, 20), (7, 21)]);
+
+        assert_eq!(summary(&entries), vec![
+            (Operation::Edit, "default", vec![20, 21]),
+            (Operation::Done, "work", vec![4]),
+            (Operation::Toggle, "default", vec![21, 9]),
+            (Operation::Edit, "work", vec![7]),
+        ]);
+    }
+
+    #[test]
+    fn partly_undone_operations_stay () {
+        let mut entries = vec![
+            entry(Operation::Edit, "default", &[1]),
+            entry(Operation::Delete, "default", &[2, 3]),
+            entry(Operation::Add, "work", &[5]),
+        ];
+
+        // Task 2 came back as 10, task 3 is left to retry
+        settle(&mut entries, "default", Some(entry(Operation::Delete, "default", &[3])), &[(2, 10)]);
+
+        assert_eq!(summary(&entries), vec![
+            (Operation::Edit, "default", vec![1]),
+            (Operation::Delete, "default", vec![3]),
+            (Operation::Add, "work", vec![5]),
+        ]);
+
+        // Nothing of the profile left to settle
+        let mut entries = vec![entry(Operation::Add, "work", &[5])];
+        settle(&mut entries, "default", None, &[(5, 6)]);
+        assert_eq!(summary(&entries), vec![(Operation::Add, "work", vec![5])]);
+    }
+}
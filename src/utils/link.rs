@@ -0,0 +1,78 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use colored::Colorize;
+use serde::{Serialize, Deserialize};
+
+use super::config_path::load_config;
+
+// Name of the file binding a directory tree to a project
+pub const LINK_FILE: &str = ".cues.toml";
+
+// Contents of a .cues.toml file
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProjectLink {
+    pub project_id: u32,
+    pub project: String,
+}
+
+// Where the current project came from
+pub enum ProjectSource {
+    // A .cues.toml file in the current directory or one of its parents
+    Link(PathBuf),
+    // The global current project, set with `cues use`
+    Global,
+}
+
+pub struct CurrentProject {
+    pub id: u32,
+    pub name: String,
+    pub source: ProjectSource,
+}
+
+// Project to work in: the nearest .cues.toml wins over the global current project
+pub fn current_project () -> Option<CurrentProject> {
+    match find_link() {
+        Some((path, Ok(link))) => return Some(CurrentProject {
+            id: link.project_id,
+            name: link.project,
+            source: ProjectSource::Link(path),
+        }),
+        Some((path, Err(e))) => eprintln!("{} Ignoring {}: {}", "".yellow(), path.display(), e),
+        None => {},
+    }
+
+    load_config()
+        .filter(|c| c.current_project_id != 0)
+        .map(|c| CurrentProject {
+            id: c.current_project_id,
+            name: c.current_project,
+            source: ProjectSource::Global,
+        })
+}
+
+// Walk up from the working directory to the nearest .cues.toml, and parse it
+pub fn find_link () -> Option<(PathBuf, Result<ProjectLink, String>)> {
+    let cwd = env::current_dir().ok()?;
+
+    cwd.ancestors()
+        .map(|dir| dir.join(LINK_FILE))
+        .find(|path| path.is_file())
+        .map(|path| {
+            let link = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|data| toml::from_str(&data).map_err(|e| e.message().to_string()));
+            (path, link)
+        })
+}
+
+// Write a .cues.toml into `dir`, returning its path
+pub fn write_link (dir: &Path, link: &ProjectLink) -> io::Result<PathBuf> {
+    let path = dir.join(LINK_FILE);
+    let data = toml::to_string(link).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    fs::write(&path, format!("# Cues project for this directory (written by `cues link`)\n{}", data))?;
+
+    Ok(path)
+}
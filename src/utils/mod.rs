@@ -3,3 +3,16 @@ pub mod types;
 pub mod display_format;
 pub mod api;
 pub mod auth;
+pub mod credentials;
+pub mod error;
+pub mod session;
+pub mod projects;
+pub mod link;
+pub mod output;
+pub mod tabular;
+pub mod filter;
+pub mod sort;
+pub mod selection;
+pub mod history;
+pub mod dates;
+pub mod editor;
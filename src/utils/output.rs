@@ -0,0 +1,107 @@
+use std::io::{self, Write};
+use std::process;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::Serialize;
+use serde_json::json;
+
+// Output format picked with --output for this run
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+// Set once anything fails, so the process can exit with a non-zero status
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+// How results are written to stdout
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Default, Debug)]
+pub enum OutputFormat {
+    // Colored text for people
+    #[default]
+    Text,
+    // One JSON document (lists become an array)
+    Json,
+    // One compact JSON object per line (lists give one line per item)
+    Jsonl,
+    // Aligned columns, one task per line (task listings only)
+    Table,
+    // Comma-separated values (task listings only)
+    Csv,
+    // Tab-separated values (task listings only)
+    Tsv,
+}
+
+// Use `format` for this run. Colors are turned off for machine-readable output
+pub fn set_format (format: OutputFormat) {
+    let _ = FORMAT.set(format);
+
+    if is_machine() {
+        colored::control::set_override(false);
+    }
+}
+
+pub fn format () -> OutputFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+// Whether output is meant for scripts rather than people
+pub fn is_machine () -> bool {
+    matches!(format(), OutputFormat::Json | OutputFormat::Jsonl)
+}
+
+// Whether task listings are printed as rows (table, csv or tsv)
+pub fn is_tabular () -> bool {
+    matches!(format(), OutputFormat::Table | OutputFormat::Csv | OutputFormat::Tsv)
+}
+
+// Write a single result
+pub fn emit<T: Serialize> (value: &T) {
+    let out = match format() {
+        OutputFormat::Json => serde_json::to_string_pretty(value),
+        _ => serde_json::to_string(value),
+    };
+
+    match out {
+        Ok(out) => write_line(&out),
+        Err(e) => emit_error("decode", &e.to_string(), None),
+    }
+}
+
+// HELPER: Write a line to stdout. A reader that went away (`| head`) just ends the run
+fn write_line (line: &str) {
+    let mut stdout = io::stdout().lock();
+
+    if let Err(e) = writeln!(stdout, "{}", line) {
+        if e.kind() == io::ErrorKind::BrokenPipe {
+            process::exit(0);
+        }
+        eprintln!("Couldn't write the output: {}", e);
+        mark_failed();
+    }
+}
+
+// Write a list of results
+pub fn emit_list<T: Serialize> (items: &[T]) {
+    match format() {
+        OutputFormat::Jsonl => items.iter().for_each(|item| emit(item)),
+        _ => emit(&items),
+    }
+}
+
+// Write an error as a JSON object on stderr
+pub fn emit_error (kind: &str, message: &str, hint: Option<&str>) {
+    mark_failed();
+
+    let mut err = json!({ "kind": kind, "message": message });
+    if let Some(hint) = hint {
+        err["hint"] = json!(hint);
+    }
+
+    eprintln!("{}", json!({ "error": err }));
+}
+
+pub fn mark_failed () {
+    FAILED.store(true, Ordering::Relaxed);
+}
+
+pub fn failed () -> bool {
+    FAILED.load(Ordering::Relaxed)
+}
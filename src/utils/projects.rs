@@ -0,0 +1,97 @@
+use std::io::{self, IsTerminal, Write};
+use colored::Colorize;
+
+use super::api::CuesClient;
+use super::error::ApiError;
+use super::output::is_machine;
+use super::types::Project;
+
+// Names at least this similar (Jaro-Winkler, 0 to 1) count as a fuzzy match
+const FUZZY_THRESHOLD: f64 = 0.8;
+
+// Find the project referred to by `query`: an id, an exact name, a case-insensitive prefix, or a fuzzy match.
+// When several projects match equally well, the user picks one from a numbered list.
+pub async fn resolve_project (client: &CuesClient, query: &str) -> Result<Project, ApiError> {
+    // Numeric ids win over names (a project may still be called "2024", so fall through when there's no such id)
+    if let Ok(id) = query.trim().parse::<u32>() {
+        match client.get_project(id).await {
+            Err(ApiError::NotFound(_)) => {},
+            res => return res,
+        }
+    }
+
+    let mut projects = client.list_projects().await?;
+    let matches = match_projects(&projects, query);
+
+    let index = match matches.as_slice() {
+        [] => return Err(ApiError::NotFound(Some(format!("No project matches \"{}\"", query)))),
+        [only] => *only,
+        _ => choose_project(&projects, &matches, query)?,
+    };
+
+    Ok(projects.swap_remove(index))
+}
+
+// Indices of the projects matching `query`, from the most specific kind of match that has any
+fn match_projects (projects: &[Project], query: &str) -> Vec<usize> {
+    let query = query.trim();
+    let lower = query.to_lowercase();
+
+    let find = |pred: &dyn Fn(&Project) -> bool| -> Vec<usize> {
+        projects.iter().enumerate().filter(|(_, p)| pred(p)).map(|(i, _)| i).collect()
+    };
+
+    let passes: [&dyn Fn(&Project) -> bool; 3] = [
+        &|p| p.name == query,
+        &|p| p.name.to_lowercase() == lower,
+        &|p| p.name.to_lowercase().starts_with(&lower),
+    ];
+
+    for pass in passes {
+        let found = find(pass);
+        if !found.is_empty() {
+            return found;
+        }
+    }
+
+    // Fuzzy: closest names first
+    let mut scored: Vec<(usize, f64)> = projects.iter()
+        .enumerate()
+        .map(|(i, p)| (i, strsim::jaro_winkler(&p.name.to_lowercase(), &lower)))
+        .filter(|(_, score)| *score >= FUZZY_THRESHOLD)
+        .collect();
+
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.into_iter().map(|(i, _)| i).collect()
+}
+
+// HELPER: Numbered chooser for an ambiguous project name (an error when there's no terminal to ask on)
+fn choose_project (projects: &[Project], matches: &[usize], query: &str) -> Result<usize, ApiError> {
+    let names: Vec<String> = matches.iter().map(|&i| format!("[{}] {}", projects[i].id, projects[i].name)).collect();
+
+    if !io::stdin().is_terminal() {
+        return Err(ApiError::Validation(format!("\"{}\" matches several projects ({}), use the project id or a longer name", query, names.join(", "))));
+    }
+
+    let mut menu = format!("\n{} Several projects match \"{}\":\n\n", "".yellow(), query);
+    for (n, name) in names.iter().enumerate() {
+        menu.push_str(&format!("  {} {}\n", format!("{}.", n + 1).yellow(), name));
+    }
+    menu.push_str(&format!("\nChoose 1-{}: ", names.len()));
+
+    // Keep stdout clean for --output json
+    if is_machine() {
+        eprint!("{}", menu);
+    } else {
+        print!("{}", menu);
+        io::stdout().flush().map_err(|e| ApiError::Validation(e.to_string()))?;
+    }
+
+    let mut choice = String::new();
+    io::stdin().read_line(&mut choice).map_err(|e| ApiError::Validation(e.to_string()))?;
+
+    match choice.trim().parse::<usize>() {
+        Ok(n) if (1..=matches.len()).contains(&n) => Ok(matches[n - 1]),
+        _ => Err(ApiError::Validation("No project was chosen".to_string())),
+    }
+}
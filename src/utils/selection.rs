@@ -0,0 +1,305 @@
+use std::future::Future;
+use std::str::FromStr;
+use clap::{Args, Parser};
+use futures::stream::{self, StreamExt};
+
+use super::api::CuesClient;
+use super::error::ApiError;
+use super::filter::TaskFilter;
+use super::link::current_project;
+use super::projects::resolve_project;
+use super::types::Task;
+
+// How many requests a bulk command keeps in flight at once
+const BULK_PARALLELISM: usize = 8;
+
+// Largest range accepted in one id argument, to catch typos like 10-1400
+const MAX_RANGE: u32 = 1000;
+
+// A task id, or an inclusive range of ids like `10-14`
+#[derive(Clone, Copy, Debug)]
+pub struct IdRange {
+    start: u32,
+    end: u32,
+}
+
+impl IdRange {
+    // The id, when this is a single id rather than a range
+    pub fn single (&self) -> Option<u32> {
+        (self.start == self.end).then_some(self.start)
+    }
+}
+
+impl FromStr for IdRange {
+    type Err = String;
+
+    fn from_str (s: &str) -> Result<Self, Self::Err> {
+        let parse = |n: &str| n.trim().parse::<u32>().map_err(|_| format!("\"{}\" is not a task id or a range like 10-14", s));
+
+        let (start, end) = match s.split_once('-') {
+            Some((a, b)) => (parse(a)?, parse(b)?),
+            None => (parse(s)?, parse(s)?),
+        };
+
+        if start > end {
+            return Err(format!("range {} goes backwards", s));
+        }
+        if end - start >= MAX_RANGE {
+            return Err(format!("range {} is too large (at most {} ids)", s, MAX_RANGE));
+        }
+
+        Ok(IdRange { start, end })
+    }
+}
+
+// Which tasks a filter looks at, and the filter itself
+#[derive(Args, Debug, Default, Clone)]
+pub struct TaskScope {
+    #[arg(long, value_name="PROJECT", help="Select tasks in this project (id or name, default: current project)", conflicts_with="all")]
+    pub project: Option<String>,
+
+    #[arg(long, help="Select tasks in all projects")]
+    pub all: bool,
+
+    #[command(flatten)]
+    pub filter: TaskFilter,
+}
+
+// Task ids given on the command line, and/or filters selecting tasks
+#[derive(Args, Debug, Default, Clone)]
+pub struct TaskSelection {
+    #[arg(value_name="IDS", help="Task ids or ranges (eg, 4 7 10-14)")]
+    pub ids: Vec<IdRange>,
+
+    #[arg(long="filter", value_name="CRITERIA", help="Select tasks with filters written as words (eg, \"pending project=3 priority=low\")")]
+    pub criteria: Option<String>,
+
+    #[command(flatten)]
+    pub scope: TaskScope,
+}
+
+// Parser for the criteria of --filter (eg, "done project=3")
+#[derive(Parser)]
+#[command(name = "--filter", no_binary_name = true, disable_help_flag = true)]
+struct FilterCriteria {
+    #[command(flatten)]
+    scope: TaskScope,
+}
+
+impl TaskScope {
+    // Whether the scope selects anything on its own
+    pub fn is_active (&self) -> bool {
+        self.project.is_some() || self.all || self.filter.is_active()
+    }
+
+    // Parse task-list criteria written as words, like "pending priority=high search='release notes'"
+    pub fn from_criteria (criteria: &str) -> Result<Self, String> {
+        let args = split_words(criteria)?
+            .into_iter()
+            .flat_map(|word| match word.split_once('=') {
+                Some((key, value)) => vec![format!("--{}", key), value.to_string()],
+                None => vec![format!("--{}", word)],
+            });
+
+        FilterCriteria::try_parse_from(args)
+            .map(|c| c.scope)
+            .map_err(|e| e.render().to_string().lines().next().unwrap_or("").trim_start_matches("error: ").to_string())
+    }
+}
+
+impl TaskSelection {
+    // Whether any ids or filters were given
+    pub fn is_empty (&self) -> bool {
+        self.ids.is_empty() && self.criteria.is_none() && !self.scope.is_active()
+    }
+
+    // The selection with its --filter criteria parsed into the scope, so both ways of filtering select the same
+    pub fn with_criteria (mut self) -> Result<Self, String> {
+        let Some(criteria) = self.criteria.take() else {
+            return Ok(self);
+        };
+
+        if self.scope.is_active() {
+            return Err("Give filters either as flags or with --filter, not both".to_string());
+        }
+
+        self.scope = TaskScope::from_criteria(&criteria)?;
+        Ok(self)
+    }
+
+    // Ids of the selected tasks, in the order given. With both ids and filters, only the ids matching the filters are kept
+    pub async fn resolve (&self, client: &CuesClient) -> Result<Vec<u32>, ApiError> {
+        let mut ids: Vec<u32> = Vec::new();
+        for range in self.ids.iter() {
+            for id in range.start..=range.end {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+
+        if !self.scope.is_active() {
+            return Ok(ids);
+        }
+
+        let matcher = self.scope.filter.matcher().map_err(ApiError::Invalid)?;
+
+        let pid = match (&self.scope.project, self.scope.all) {
+            (_, true) => None,
+            (Some(query), _) => Some(resolve_project(client, query).await?.id),
+            (None, false) => Some(current_project().map(|p| p.id).unwrap_or(0)),
+        };
+
+        let matching: Vec<u32> = client.list_tasks().await?
+            .iter()
+            .filter(|t| pid.is_none_or(|pid| t.project_id == pid) && matcher.matches(t))
+            .map(|t| t.id)
+            .collect();
+
+        if self.ids.is_empty() {
+            Ok(matching)
+        } else {
+            Ok(ids.into_iter().filter(|id| matching.contains(id)).collect())
+        }
+    }
+}
+
+// Run `op` for every id concurrently (a few at a time), returning the results in the order of `ids`
+pub async fn run_bulk<F, Fut> (ids: &[u32], op: F) -> Vec<(u32, Result<Task, ApiError>)>
+where
+    F: Fn(u32) -> Fut,
+    Fut: Future<Output = Result<Task, ApiError>>,
+{
+    let mut results: Vec<(usize, u32, Result<Task, ApiError>)> = stream::iter(ids.iter().copied().enumerate())
+        .map(|(i, id)| {
+            let fut = op(id);
+            async move { (i, id, fut.await) }
+        })
+        .buffer_unordered(BULK_PARALLELISM)
+        .collect()
+        .await;
+
+    results.sort_by_key(|(i, _, _)| *i);
+    results.into_iter().map(|(_, id, res)| (id, res)).collect()
+}
+
+// HELPER: Split criteria into words, keeping quoted phrases together
+fn split_words (input: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut cur = String::new();
+    let mut quote: Option<char> = None;
+    let mut in_word = false;
+
+    for ch in input.chars() {
+        match (quote, ch) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => cur.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(ch);
+                in_word = true;
+            },
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut cur));
+                    in_word = false;
+                }
+            },
+            (None, c) => {
+                cur.push(c);
+                in_word = true;
+            },
+        }
+    }
+
+    if quote.is_some() {
+        return Err("unclosed quote in --filter".to_string());
+    }
+    if in_word {
+        words.push(cur);
+    }
+
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_ranges () {
+        let cases = [
+            ("4", Some((4, 4))),
+            ("10-14", Some((10, 14))),
+            ("7-7", Some((7, 7))),
+            ("1-1000", Some((1, 1000))),
+            ("1-1001", None),
+            ("14-10", None),
+            ("-5", None),
+            ("5-", None),
+            ("4-5-6", None),
+            ("abc", None),
+            ("", None),
+        ];
+
+        for (input, expected) in cases {
+            let parsed = input.parse::<IdRange>().ok().map(|r| (r.start, r.end));
+            assert_eq!(parsed, expected, "parsing {:?}", input);
+        }
+    }
+
+    #[test]
+    fn words () {
+        let cases = [
+            ("pending project=3", Some(vec!["pending", "project=3"])),
+            ("  done   all ", Some(vec!["done", "all"])),
+            ("search='release notes' done", Some(vec!["search=release notes", "done"])),
+            ("search=\"it's late\"", Some(vec!["search=it's late"])),
+            ("search=''", Some(vec!["search="])),
+            ("", Some(vec![])),
+            ("search='release notes", None),
+            ("search=\"oops", None),
+        ];
+
+        for (input, expected) in cases {
+            let expected = expected.map(|words| words.into_iter().map(String::from).collect::<Vec<_>>());
+            assert_eq!(split_words(input).ok(), expected, "splitting {:?}", input);
+        }
+    }
+
+    #[test]
+    fn criteria () {
+        let scope = TaskScope::from_criteria("pending priority=high search='release notes' due-before=friday").unwrap();
+        assert!(scope.filter.pending && !scope.filter.done);
+        assert!(scope.filter.priority.is_some());
+        assert_eq!(scope.filter.search.as_deref(), Some("release notes"));
+        assert_eq!(scope.filter.due_before.as_deref(), Some("friday"));
+        assert!(scope.is_active());
+
+        let scope = TaskScope::from_criteria("project='Mobile App' done").unwrap();
+        assert_eq!(scope.project.as_deref(), Some("Mobile App"));
+        assert!(scope.filter.done);
+
+        assert!(!TaskScope::from_criteria("").unwrap().is_active());
+        assert!(TaskScope::from_criteria("pending done").is_err());
+        assert!(TaskScope::from_criteria("project=3 all").is_err());
+        assert!(TaskScope::from_criteria("colour=red").is_err());
+        assert!(TaskScope::from_criteria("priority=urgent").is_err());
+        assert!(TaskScope::from_criteria("search='unclosed").is_err());
+    }
+
+    #[test]
+    fn criteria_as_selection () {
+        let from = |criteria: &str, scope: TaskScope| TaskSelection { ids: Vec::new(), criteria: Some(criteria.to_string()), scope }.with_criteria();
+
+        let selection = from("done project=3", TaskScope::default()).unwrap();
+        assert!(selection.criteria.is_none());
+        assert!(selection.scope.filter.done);
+        assert_eq!(selection.scope.project.as_deref(), Some("3"));
+
+        let flags = TaskScope { all: true, ..TaskScope::default() };
+        assert!(from("done", flags).is_err());
+        assert!(from("search='oops", TaskScope::default()).is_err());
+
+        assert!(!TaskSelection { criteria: Some(String::new()), ..TaskSelection::default() }.is_empty());
+    }
+}
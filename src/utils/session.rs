@@ -0,0 +1,101 @@
+use std::env;
+use colored::Colorize;
+
+use super::api::CuesClient;
+use super::auth::{jwt_expired, token_expiry, AuthStore, ACCESS_TOKEN, REFRESH_TOKEN};
+use super::credentials::{CredentialError, CredentialStore};
+use super::config_path::{load_config, save_config};
+use super::error::ApiError;
+
+// Logged-in session: owns the stored credentials and keeps the access token valid
+pub struct Session {
+    store: AuthStore,
+}
+
+impl Session {
+    // Exchange the stored refresh token for a new access token, and persist it along with its expiry
+    pub async fn refresh (&self, client: &CuesClient) -> Result<String, ApiError> {
+        let refresh_token = self.store.get(REFRESH_TOKEN)?;
+
+        let tokens = client.refresh(&refresh_token).await?;
+        let expires_at = token_expiry(&tokens);
+
+        // A failed write only costs another refresh next time, so warn instead of failing the command
+        if let Err(e) = self.store.set(ACCESS_TOKEN, &tokens.access_token) {
+            eprintln!("{} Couldn't save the refreshed access token: {}", "".yellow(), e);
+        }
+
+        // Some backends rotate the refresh token as well
+        if let Some(rotated) = &tokens.refresh_token {
+            if let Err(e) = self.store.set(REFRESH_TOKEN, rotated) {
+                eprintln!("{} Couldn't save the new refresh token: {}", "".yellow(), e);
+            }
+        }
+
+        if let Some(mut config) = load_config() {
+            config.expires_at = expires_at;
+
+            if let Err(e) = save_config(&config) {
+                eprintln!("{} Couldn't save the token expiry: {}", "".yellow(), e);
+            }
+        }
+
+        Ok(tokens.access_token)
+    }
+}
+
+// Returns a client authenticated as the logged-in user, refreshing the access token first if it has expired.
+//
+// For CI and scripts, credentials can also come from the environment without touching the keyring:
+// CUES_TOKEN is used as-is, and CUES_USERNAME/CUES_PASSWORD log in for the duration of the command.
+pub async fn connect (store: AuthStore, base_url: &str) -> Result<CuesClient, ApiError> {
+    let mut client = CuesClient::new(base_url);
+
+    if let Some(token) = env_var("CUES_TOKEN") {
+        client.set_token(token);
+        return Ok(client);
+    }
+
+    let stored = match store.get(ACCESS_TOKEN) {
+        Ok(token) => Ok(load_config().map(|config| (config, token))),
+        Err(CredentialError::Missing) => Ok(None),
+        // The keyring being unusable just means nothing can be stored there, the environment may still have credentials
+        Err(e @ CredentialError::Keyring(_)) if store.kind() == CredentialStore::Keyring => Err(e),
+        Err(e) => return Err(e.into()),
+    };
+
+    let (config, token) = match stored {
+        Ok(Some(found)) => found,
+        // Nothing stored locally, fall back to credentials from the environment
+        missing => {
+            let (Some(user), Some(password)) = (env_var("CUES_USERNAME"), env_var("CUES_PASSWORD")) else {
+                // Without them, an unusable keyring is the actual problem (and has its own hint)
+                return Err(match missing {
+                    Err(e) => e.into(),
+                    Ok(_) => ApiError::NotLoggedIn,
+                });
+            };
+
+            let tokens = client.login(&user, &password).await?;
+            client.set_token(tokens.access_token);
+            return Ok(client);
+        },
+    };
+
+    let session = Session { store };
+    client.set_token(token);
+
+    if jwt_expired(config.expires_at.as_str()) {
+        let token = session.refresh(&client).await?;
+        client.set_token(token);
+    }
+
+    client.attach_session(session);
+
+    Ok(client)
+}
+
+// HELPER: Read a non-empty environment variable
+pub fn env_var (name: &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
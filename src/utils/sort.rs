@@ -0,0 +1,89 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use chrono::{DateTime, Local, Utc};
+use clap::Args;
+
+use super::types::{PriorityType, Task};
+
+// Fields a task listing can be sorted by
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Debug)]
+pub enum TaskSort {
+    Due,
+    Priority,
+    Created,
+    Id,
+    Title,
+}
+
+// Order of a task listing
+#[derive(Args, Debug, Default, Clone)]
+pub struct TaskOrder {
+    #[arg(long, value_enum, help="Sort by this field (default: overdue first, then by due date, then by priority)")]
+    pub sort: Option<TaskSort>,
+
+    #[arg(long, help="Reverse the order")]
+    pub reverse: bool,
+}
+
+impl TaskOrder {
+    // Sort tasks in place. Ties keep falling back to the next field, and finally to the id
+    pub fn apply<T: Borrow<Task>> (&self, tasks: &mut [T]) {
+        let now = Local::now();
+
+        tasks.sort_by(|a, b| {
+            let (a, b) = (a.borrow(), b.borrow());
+
+            let order = match self.sort {
+                None => by_overdue(a, b, now).then_with(|| by_due(a, b)).then_with(|| by_priority(a, b)),
+                Some(TaskSort::Due) => by_due(a, b).then_with(|| by_priority(a, b)),
+                Some(TaskSort::Priority) => by_priority(a, b).then_with(|| by_due(a, b)),
+                Some(TaskSort::Created) => created(a).cmp(&created(b)),
+                Some(TaskSort::Id) => Ordering::Equal,
+                Some(TaskSort::Title) => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            }
+            .then_with(|| a.id.cmp(&b.id));
+
+            if self.reverse { order.reverse() } else { order }
+        });
+    }
+}
+
+// HELPER: Overdue (pending and past due) tasks first
+fn by_overdue (a: &Task, b: &Task, now: DateTime<Local>) -> Ordering {
+    let overdue = |t: &Task| !t.is_done && due(t).is_some_and(|d| d < now);
+    overdue(b).cmp(&overdue(a))
+}
+
+// HELPER: Earliest due date first, tasks without one last
+fn by_due (a: &Task, b: &Task) -> Ordering {
+    match (due(a), due(b)) {
+        (Some(x), Some(y)) => x.cmp(&y),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+// HELPER: High priority first, tasks without one last
+fn by_priority (a: &Task, b: &Task) -> Ordering {
+    let rank = |t: &Task| match t.priority {
+        Some(PriorityType::High) => 0,
+        Some(PriorityType::Medium) => 1,
+        Some(PriorityType::Low) => 2,
+        None => 3,
+    };
+
+    rank(a).cmp(&rank(b))
+}
+
+// HELPER: Due date of a task
+fn due (task: &Task) -> Option<DateTime<Local>> {
+    task.due.as_deref()
+        .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
+        .map(|d| d.with_timezone(&Local))
+}
+
+// HELPER: Creation time of a task (unparseable dates sort first)
+fn created (task: &Task) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&task.created_at).ok().map(|d| d.with_timezone(&Utc))
+}
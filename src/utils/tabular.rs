@@ -0,0 +1,214 @@
+use std::error::Error;
+use std::io;
+use chrono::DateTime;
+use colored::{ColoredString, Colorize};
+use terminal_size::{terminal_size, Width};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use super::dates::{paint_due, show_short_date, urgency};
+use super::output::{format, OutputFormat};
+use super::types::{PriorityType, Project, Task};
+
+// Columns shown by --output table when --columns isn't given (csv and tsv show every column)
+const DEFAULT_COLUMNS: [TaskColumn; 6] = [
+    TaskColumn::Id,
+    TaskColumn::Status,
+    TaskColumn::Priority,
+    TaskColumn::Title,
+    TaskColumn::Due,
+    TaskColumn::Project,
+];
+
+// Columns are never truncated below this width to fit the terminal
+const MIN_WIDTH: usize = 8;
+
+// Space between table columns
+const GAP: &str = "  ";
+
+// Task fields that can be picked with --columns
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Debug)]
+pub enum TaskColumn {
+    Id,
+    Status,
+    Priority,
+    Title,
+    Description,
+    Due,
+    Project,
+    Created,
+}
+
+impl TaskColumn {
+    // Header of this column
+    fn name (&self) -> &'static str {
+        match self {
+            TaskColumn::Id => "id",
+            TaskColumn::Status => "status",
+            TaskColumn::Priority => "priority",
+            TaskColumn::Title => "title",
+            TaskColumn::Description => "description",
+            TaskColumn::Due => "due",
+            TaskColumn::Project => "project",
+            TaskColumn::Created => "created",
+        }
+    }
+
+    // Value of this column for a task. `raw` keeps dates as sent by the backend (for csv/tsv)
+    fn value (&self, task: &Task, projects: &[Project], raw: bool) -> String {
+        let date = |d: &str, done: Option<bool>| match DateTime::parse_from_rfc3339(d) {
+            Ok(dt) if !raw => show_short_date(dt, done),
+            _ => d.to_string(),
+        };
+
+        match self {
+            TaskColumn::Id => task.id.to_string(),
+            TaskColumn::Status => if task.is_done { "done" } else { "pending" }.to_string(),
+            TaskColumn::Priority => task.priority.as_ref().map(|p| format!("{:?}", p)).unwrap_or_default(),
+            TaskColumn::Title => task.title.clone(),
+            TaskColumn::Description => task.description.as_deref().unwrap_or("").replace(['\r', '\n'], " "),
+            TaskColumn::Due => task.due.as_deref().map(|d| date(d, Some(task.is_done))).unwrap_or_default(),
+            TaskColumn::Project => projects.iter()
+                .find(|p| p.id == task.project_id)
+                .map(|p| p.name.clone())
+                .unwrap_or_else(|| task.project_id.to_string()),
+            TaskColumn::Created => date(&task.created_at, None),
+        }
+    }
+
+    // Color of a table cell
+    fn paint (&self, task: &Task, text: String) -> ColoredString {
+        match self {
+            TaskColumn::Id => text.yellow(),
+            TaskColumn::Status if task.is_done => text.green(),
+            TaskColumn::Status => text.red(),
+            TaskColumn::Priority => match task.priority {
+                Some(PriorityType::High) => text.red(),
+                Some(PriorityType::Medium) => text.yellow(),
+                Some(PriorityType::Low) => text.green(),
+                None => text.normal(),
+            },
+            TaskColumn::Due => {
+                let due = task.due.as_deref().and_then(|d| DateTime::parse_from_rfc3339(d).ok());
+                paint_due(&text, due.and_then(|d| urgency(d, task.is_done)))
+            },
+            TaskColumn::Description | TaskColumn::Created => text.dimmed(),
+            _ => text.normal(),
+        }
+    }
+}
+
+// Print tasks as rows, in the format picked with --output (table, csv or tsv)
+pub fn print_tasks_tabular (tasks: &[&Task], projects: &[Project], columns: &[TaskColumn]) -> Result<(), Box<dyn Error>> {
+    match format() {
+        OutputFormat::Csv => write_delimited(tasks, projects, columns, b','),
+        OutputFormat::Tsv => write_delimited(tasks, projects, columns, b'\t'),
+        _ => {
+            print_table(tasks, projects, columns);
+            Ok(())
+        },
+    }
+}
+
+// HELPER: Aligned table, with wide columns truncated to fit the terminal
+fn print_table (tasks: &[&Task], projects: &[Project], columns: &[TaskColumn]) {
+    let columns = if columns.is_empty() { &DEFAULT_COLUMNS[..] } else { columns };
+
+    let rows: Vec<Vec<String>> = tasks.iter()
+        .map(|task| columns.iter().map(|c| c.value(task, projects, false)).collect())
+        .collect();
+
+    let mut widths: Vec<usize> = columns.iter().map(|c| c.name().width()).collect();
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+
+    // Only shrink when writing to a terminal, pipes get the full text
+    if let Some((Width(term), _)) = terminal_size() {
+        fit_widths(&mut widths, columns, term as usize);
+    }
+
+    let header: Vec<String> = columns.iter()
+        .zip(&widths)
+        .map(|(c, w)| pad(&truncate(&c.name().to_uppercase(), *w), *w))
+        .collect();
+    println!("{}", header.join(GAP).trim_end().bold());
+
+    for (task, row) in tasks.iter().zip(rows) {
+        let cells: Vec<String> = columns.iter()
+            .zip(&widths)
+            .zip(row)
+            .enumerate()
+            .map(|(i, ((c, w), cell))| {
+                let cell = truncate(&cell, *w);
+                // No trailing padding after the last column
+                let cell = if i + 1 == columns.len() { cell } else { pad(&cell, *w) };
+                c.paint(task, cell).to_string()
+            })
+            .collect();
+
+        println!("{}", cells.join(GAP));
+    }
+}
+
+// HELPER: Comma or tab separated values, with a header row
+fn write_delimited (tasks: &[&Task], projects: &[Project], columns: &[TaskColumn], delimiter: u8) -> Result<(), Box<dyn Error>> {
+    let all = <TaskColumn as clap::ValueEnum>::value_variants();
+    let columns = if columns.is_empty() { all } else { columns };
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(io::stdout());
+
+    writer.write_record(columns.iter().map(|c| c.name()))?;
+    for task in tasks {
+        writer.write_record(columns.iter().map(|c| c.value(task, projects, true)))?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+// HELPER: Shrink the free-text columns until the table fits in `term` columns
+fn fit_widths (widths: &mut [usize], columns: &[TaskColumn], term: usize) {
+    let total = |widths: &[usize]| widths.iter().sum::<usize>() + GAP.len() * widths.len().saturating_sub(1);
+
+    for shrinkable in [TaskColumn::Description, TaskColumn::Title, TaskColumn::Project] {
+        let Some(i) = columns.iter().position(|c| *c == shrinkable) else { continue };
+
+        let excess = total(widths).saturating_sub(term);
+        if excess == 0 {
+            return;
+        }
+
+        widths[i] = widths[i].saturating_sub(excess).max(MIN_WIDTH.min(widths[i]));
+    }
+}
+
+// HELPER: Cut text to `width` display columns, marking the cut with an ellipsis
+fn truncate (text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+
+    let mut out = String::new();
+    let mut used = 0;
+    for ch in text.chars() {
+        let w = ch.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(ch);
+        used += w;
+    }
+
+    out.push('…');
+    out
+}
+
+// HELPER: Pad text with spaces to `width` display columns
+fn pad (text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(text.width())))
+}
@@ -22,7 +22,7 @@ pub struct Project {
     pub created_at: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum PriorityType {
     High,
     Medium,
@@ -46,3 +46,16 @@ pub struct Task {
     #[serde(rename="createdAt")]
     pub created_at: String,
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuthTokens {
+    #[serde(rename="accessToken")]
+    pub access_token: String,
+
+    #[serde(rename="refreshToken")]
+    pub refresh_token: Option<String>,
+
+    // Token lifetime, either seconds or a duration string like "15m"
+    #[serde(rename="expiresIn")]
+    pub expires_in: Option<serde_json::Value>,
+}